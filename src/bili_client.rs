@@ -0,0 +1,384 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::{
+    header::{HeaderMap, HeaderValue, REFERER},
+    Client, RequestBuilder, Response, StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use tokio::{
+    sync::{Mutex, RwLock, Semaphore},
+    time::Instant,
+};
+
+use crate::{error::AppError, session::client_builder};
+
+/// 请求 B 站接口和音频流时使用的 User-Agent
+pub const BILI_USER_AGENT: &str = "Mozilla/5.0 BiliDroid/..* (bbcallen@gmail.com)";
+pub const BILI_REFERER: &str = "https://www.bilibili.com";
+
+const MIXIN_KEY_ENC_TAB: [usize; 64] = [
+    46, 47, 18, 2, 53, 8, 23, 32, 15, 50, 10, 31, 58, 3, 45, 35, 27, 43, 5, 49, 33, 9, 42, 19, 29,
+    28, 14, 39, 12, 38, 41, 13, 37, 48, 7, 16, 24, 55, 40, 61, 26, 17, 0, 1, 60, 51, 30, 4, 22, 25,
+    54, 21, 56, 59, 6, 63, 57, 62, 11, 36, 20, 34, 44, 52,
+];
+/// WBI 密钥每天更新, 缓存超过该时间后重新获取
+const WBI_KEY_TTL: Duration = Duration::from_secs(60 * 60);
+/// 同时进行的请求数上限
+const MAX_CONCURRENT_REQUESTS: usize = 4;
+/// 令牌桶每秒补充的请求数和桶的容量
+const REQUESTS_PER_SECOND: f64 = 4.0;
+const BURST_SIZE: f64 = 8.0;
+/// 触发风控后的首次等待时间, 之后每次翻倍
+const RISK_CONTROL_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_RISK_CONTROL_RETRIES: u32 = 3;
+
+/// B 站接口统一的返回格式
+#[derive(Deserialize)]
+struct Envelope<T> {
+    code: i64,
+    #[serde(default)]
+    message: String,
+    data: Option<T>,
+}
+
+fn api_error(code: i64, message: String) -> AppError {
+    match code {
+        -101 => AppError::NotLoggedIn,
+        -403 => AppError::AccessDenied(message),
+        -404 => AppError::NotFound(message),
+        -352 | -412 => AppError::RiskControl(code),
+        // 稿件不可见、审核中、仅 UP 主自己可见
+        62002 | 62004 | 62012 => AppError::VideoPrivate(message),
+        -10403 | 6_002_003 => AppError::RegionLocked(message),
+        _ => AppError::Api { code, message },
+    }
+}
+
+#[derive(Deserialize)]
+struct WbiImg {
+    img_url: String,
+    sub_url: String,
+}
+
+/// 当前登录用户的信息, 未登录时 `is_login` 为 false
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NavInfo {
+    pub is_login: bool,
+    #[serde(default)]
+    pub uname: String,
+    #[serde(default)]
+    pub mid: i64,
+    #[serde(default)]
+    pub vip_status: i32,
+    wbi_img: WbiImg,
+}
+
+#[derive(Debug)]
+struct WbiKey {
+    mixin_key: String,
+    fetched_at: SystemTime,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    /// 触发风控后在该时间之前暂停所有请求
+    paused_until: Option<Instant>,
+}
+
+/// 令牌桶限流, 所有克隆出的客户端共享同一个桶
+#[derive(Debug)]
+struct RateLimiter {
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    fn new() -> Self {
+        RateLimiter {
+            bucket: Mutex::new(Bucket {
+                tokens: BURST_SIZE,
+                refilled_at: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// 等待直到可以发出下一个请求
+    async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+                match bucket.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+                        bucket.tokens =
+                            (bucket.tokens + elapsed * REQUESTS_PER_SECOND).min(BURST_SIZE);
+                        bucket.refilled_at = now;
+                        if bucket.tokens >= 1.0 {
+                            bucket.tokens -= 1.0;
+                            return;
+                        }
+                        Duration::from_secs_f64((1.0 - bucket.tokens) / REQUESTS_PER_SECOND)
+                    }
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// 触发风控后暂停所有请求一段时间, 并清空令牌避免恢复后立即集中请求
+    async fn pause(&self, duration: Duration) {
+        let mut bucket = self.bucket.lock().await;
+        let until = Instant::now() + duration;
+        bucket.paused_until = Some(bucket.paused_until.map_or(until, |old| old.max(until)));
+        bucket.tokens = 0.0;
+        bucket.refilled_at = until;
+    }
+}
+
+/// 共享的 B 站接口客户端, 带有登录 cookie 和通用请求头, 负责 WBI 签名和返回结果的解析.
+/// 所有接口请求都经过限流和并发限制, 触发风控时暂停一段时间后重试
+#[derive(Clone, Debug)]
+pub struct BiliClient {
+    client: Client,
+    wbi_key: Arc<RwLock<Option<WbiKey>>>,
+    limiter: Arc<RateLimiter>,
+    permits: Arc<Semaphore>,
+}
+
+impl BiliClient {
+    pub fn new() -> Result<Self, AppError> {
+        Self::build(None)
+    }
+
+    pub fn with_timeout(timeout: Duration) -> Result<Self, AppError> {
+        Self::build(Some(timeout))
+    }
+
+    fn build(timeout: Option<Duration>) -> Result<Self, AppError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(REFERER, HeaderValue::from_static(BILI_REFERER));
+        let mut builder = client_builder()?
+            .user_agent(BILI_USER_AGENT)
+            .default_headers(headers);
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        Ok(BiliClient {
+            client: builder.build()?,
+            wbi_key: Arc::new(RwLock::new(None)),
+            limiter: Arc::new(RateLimiter::new()),
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS)),
+        })
+    }
+
+    /// 底层的 HTTP 客户端, 用于请求非 API 的地址
+    pub fn http(&self) -> &Client {
+        &self.client
+    }
+
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, AppError> {
+        self.request(url, || self.client.get(url).query(params))
+            .await
+    }
+
+    /// 需要 WBI 签名的接口, 签名失败 (-403) 时刷新密钥后重试一次
+    pub async fn get_wbi<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, AppError> {
+        match self.get_signed(url, params, false).await {
+            Err(AppError::AccessDenied(_)) => self.get_signed(url, params, true).await,
+            result => result,
+        }
+    }
+
+    async fn get_signed<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &[(&str, &str)],
+        refresh_key: bool,
+    ) -> Result<T, AppError> {
+        let mixin_key = self.mixin_key(refresh_key).await?;
+        self.request(url, || {
+            let query = sign_params(params, &mixin_key, unix_now());
+            self.client.get(format!("{url}?{query}"))
+        })
+        .await
+    }
+
+    /// 发送请求并解析结果, 遇到风控时按指数退避重试
+    async fn request<T, F>(&self, url: &str, build: F) -> Result<T, AppError>
+    where
+        T: DeserializeOwned,
+        F: Fn() -> RequestBuilder,
+    {
+        let mut backoff = RISK_CONTROL_BACKOFF;
+        let mut retries = 0;
+        loop {
+            let result = match self.send(build()).await {
+                Ok(response) => decode(url, response).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Err(AppError::RiskControl(code)) if retries < MAX_RISK_CONTROL_RETRIES => {
+                    log::warn!(
+                        "Request to {url} blocked by risk control ({code}), retrying in {}s",
+                        backoff.as_secs()
+                    );
+                    self.limiter.pause(backoff).await;
+                    backoff *= 2;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// 经过并发限制和限流后发出请求, HTTP 412 视为触发风控
+    async fn send(&self, request: RequestBuilder) -> Result<Response, AppError> {
+        let _permit = self.permits.acquire().await?;
+        self.limiter.acquire().await;
+        let response = request.send().await?;
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Err(AppError::RiskControl(-412));
+        }
+        Ok(response)
+    }
+
+    pub async fn nav(&self) -> Result<NavInfo, AppError> {
+        let url = "https://api.bilibili.com/x/web-interface/nav";
+        let response = self.send(self.client.get(url)).await?;
+        // 未登录时 code 为 -101, 但 data 中仍然包含 WBI 密钥
+        let envelope: Envelope<NavInfo> = response.json().await?;
+        envelope
+            .data
+            .ok_or_else(|| api_error(envelope.code, envelope.message))
+    }
+
+    async fn mixin_key(&self, refresh: bool) -> Result<String, AppError> {
+        if !refresh {
+            let cached = self.wbi_key.read().await;
+            if let Some(key) = cached.as_ref().filter(|key| {
+                key.fetched_at
+                    .elapsed()
+                    .is_ok_and(|elapsed| elapsed < WBI_KEY_TTL)
+            }) {
+                return Ok(key.mixin_key.clone());
+            }
+        }
+        let nav = self.nav().await?;
+        let mixin_key = mixin_key(&nav.wbi_img.img_url, &nav.wbi_img.sub_url);
+        *self.wbi_key.write().await = Some(WbiKey {
+            mixin_key: mixin_key.clone(),
+            fetched_at: SystemTime::now(),
+        });
+        Ok(mixin_key)
+    }
+}
+
+async fn decode<T: DeserializeOwned>(url: &str, response: Response) -> Result<T, AppError> {
+    let envelope: Envelope<T> = response.json().await.map_err(|e| {
+        log::error!("Failed to parse response from {url}: {e}");
+        AppError::HttpRequest(e)
+    })?;
+    if envelope.code != 0 {
+        return Err(api_error(envelope.code, envelope.message));
+    }
+    envelope
+        .data
+        .ok_or_else(|| AppError::DataParsing(format!("{url} 返回的数据为空")))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// 取 img_url 和 sub_url 的文件名拼接后按固定的表打乱, 得到 32 位的混合密钥
+fn mixin_key(img_url: &str, sub_url: &str) -> String {
+    let stem = |url: &str| -> String {
+        let name = url.rsplit('/').next().unwrap_or_default();
+        name.split('.').next().unwrap_or_default().to_string()
+    };
+    let raw: Vec<char> = format!("{}{}", stem(img_url), stem(sub_url))
+        .chars()
+        .collect();
+    MIXIN_KEY_ENC_TAB
+        .iter()
+        .filter_map(|&i| raw.get(i))
+        .take(32)
+        .collect()
+}
+
+/// 与 JavaScript 的 encodeURIComponent 相同的编码方式
+fn encode_component(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// 加入 wts 后按参数名排序, 去掉值中的 `!'()*`, 以 md5(query + mixin_key) 作为 w_rid
+fn sign_params(params: &[(&str, &str)], mixin_key: &str, wts: u64) -> String {
+    let wts = wts.to_string();
+    let mut params: Vec<(&str, String)> = params
+        .iter()
+        .chain(std::iter::once(&("wts", wts.as_str())))
+        .map(|(k, v)| (*k, v.chars().filter(|c| !"!'()*".contains(*c)).collect()))
+        .collect();
+    params.sort_by(|a, b| a.0.cmp(b.0));
+    let query = params
+        .iter()
+        .map(|(k, v)| format!("{}={}", encode_component(k), encode_component(v)))
+        .collect::<Vec<String>>()
+        .join("&");
+    let w_rid = md5::compute(format!("{query}{mixin_key}"));
+    format!("{query}&w_rid={w_rid:x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // bilibili-API-collect 文档中 WBI 签名的示例
+    const IMG_URL: &str = "https://i0.hdslb.com/bfs/wbi/7cd084941338484aae1ad9425b84077c.png";
+    const SUB_URL: &str = "https://i0.hdslb.com/bfs/wbi/4932caff0ff746eab6f01bf08b70ac45.png";
+
+    #[test]
+    fn mixin_key_matches_example() {
+        assert_eq!(
+            mixin_key(IMG_URL, SUB_URL),
+            "ea1db124af3c7062474693fa704f4ff8"
+        );
+    }
+
+    #[test]
+    fn sign_params_matches_example() {
+        let key = mixin_key(IMG_URL, SUB_URL);
+        let params = [("foo", "114"), ("bar", "514"), ("zab", "1919810")];
+        assert_eq!(
+            sign_params(&params, &key, 1702204169),
+            "bar=514&foo=114&wts=1702204169&zab=1919810&w_rid=8f6f2b5b3d485fe1886cec6a0be8c5d4"
+        );
+    }
+}
@@ -1,16 +1,42 @@
-use reqwest::Client;
-use rosesong::error::AppError;
-use serde_json::Value;
+use rosesong::{bili_client::BiliClient, error::AppError};
+use serde::Deserialize;
 
-const BASE_API_URL: &str = "https://api.bilibili.com/x/player/playurl?fnval=16";
+const PLAYURL_API: &str = "https://api.bilibili.com/x/player/wbi/playurl";
 
-pub async fn fetch_audio_url(client: &Client, bvid: &str, cid: &str) -> Result<String, AppError> {
-    let url = format!("{BASE_API_URL}&bvid={bvid}&cid={cid}");
+#[derive(Deserialize)]
+struct DashAudio {
+    #[serde(rename = "baseUrl")]
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct Dash {
+    #[serde(default)]
+    audio: Vec<DashAudio>,
+}
+
+#[derive(Deserialize)]
+struct PlayUrl {
+    dash: Option<Dash>,
+}
+
+pub async fn fetch_audio_url(
+    client: &BiliClient,
+    bvid: &str,
+    cid: &str,
+) -> Result<String, AppError> {
     log::info!("Fetching audio URL");
-    let response = client.get(&url).send().await?;
-    let json: Value = response.json().await?;
-    json["data"]["dash"]["audio"][0]["baseUrl"]
-        .as_str()
-        .map(std::string::ToString::to_string)
+    let params = [("bvid", bvid), ("cid", cid), ("fnval", "16")];
+    let play_url: PlayUrl = client
+        .get_wbi(PLAYURL_API, &params)
+        .await
+        .map_err(|e| match e {
+            AppError::NotFound(_) => AppError::VideoDeleted(bvid.to_string()),
+            e => e,
+        })?;
+    play_url
+        .dash
+        .and_then(|dash| dash.audio.into_iter().next())
+        .map(|audio| audio.base_url)
         .ok_or_else(|| AppError::DataParsing("解析音频URL失败".to_string()))
 }
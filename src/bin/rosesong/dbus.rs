@@ -1,19 +1,46 @@
-use std::sync::Arc;
-
 use log::info;
-use rosesong::model::PlayMode;
-use tokio::sync::{mpsc, watch, Mutex};
+use rosesong::model::{PlayMode, PlaylistChange, SourceImport, TrackId, TrackPatch, TrackRemoval};
+use serde::de::DeserializeOwned;
+use tokio::sync::{mpsc, watch};
 use zbus::{fdo, interface, ConnectionBuilder};
 
-use crate::player::{playlist::update_current_play_tracks, Command};
+use crate::mpris::{start_mpris_notifier, MprisPlayer, MprisRoot, MPRIS_NAME, MPRIS_PATH};
+use crate::player::{
+    playlist::{apply_playlist_change, get_current_track, PLAYLIST},
+    Audio, Command,
+};
 
 #[derive(Clone)]
 pub struct PlayerDBus {
     tx: mpsc::Sender<Command>,
     stop_signal: watch::Sender<()>,
-    playlist_empty: Arc<Mutex<bool>>,
+    audio: Audio,
+}
+
+fn parse_json<T: DeserializeOwned>(json: &str) -> fdo::Result<T> {
+    serde_json::from_str(json).map_err(|e| fdo::Error::InvalidArgs(e.to_string()))
 }
 
+impl PlayerDBus {
+    // 歌曲列表只由 rosesong 修改, 歌曲变化后重新加载当前播放列表
+    async fn change_playlist(&self, change: PlaylistChange) -> fdo::Result<u32> {
+        let (count, tracks_changed) = apply_playlist_change(change)
+            .await
+            .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+        if tracks_changed {
+            self.tx.send(Command::ReloadPlaylist).await.unwrap();
+        }
+        Ok(u32::try_from(count).unwrap_or(u32::MAX))
+    }
+}
+
+/// rsg 控制 rosesong 的接口.
+///
+/// 修改歌曲列表的方法 (`AddTracks`、`ImportSource`、`UpdateTracks`、`RemoveTracks`、
+/// `ApplyChanges`) 的参数是 `model` 中对应类型的 JSON 字符串, 而不是 D-Bus 类型:
+/// 这些类型包含带数据的枚举和大量 `Option` 字段, D-Bus 没有对应的表示, 用 JSON
+/// 可以直接复用歌曲列表文件的 serde 定义, 新增可选字段时也不会改变方法签名.
+/// 解析失败时返回 `org.freedesktop.DBus.Error.InvalidArgs`.
 #[interface(name = "org.rosesong.Player")]
 impl PlayerDBus {
     #[allow(clippy::unused_self)]
@@ -24,8 +51,8 @@ impl PlayerDBus {
         Ok(())
     }
 
-    async fn play_bvid(&self, bvid: String) -> fdo::Result<()> {
-        self.tx.send(Command::PlayBvid(bvid)).await.unwrap();
+    async fn play_track(&self, id: TrackId) -> fdo::Result<()> {
+        self.tx.send(Command::PlayTrack(id)).await.unwrap();
         Ok(())
     }
 
@@ -34,6 +61,21 @@ impl PlayerDBus {
         Ok(())
     }
 
+    async fn play_series(&self, id: String) -> fdo::Result<()> {
+        self.tx.send(Command::PlaySeries(id)).await.unwrap();
+        Ok(())
+    }
+
+    async fn play_tag(&self, tag: String) -> fdo::Result<()> {
+        self.tx.send(Command::PlayTag(tag)).await.unwrap();
+        Ok(())
+    }
+
+    async fn play_smart(&self, name: String) -> fdo::Result<()> {
+        self.tx.send(Command::PlaySmart(name)).await.unwrap();
+        Ok(())
+    }
+
     async fn play_all(&self) -> fdo::Result<()> {
         self.tx.send(Command::PlayAll).await.unwrap();
         Ok(())
@@ -73,41 +115,90 @@ impl PlayerDBus {
         Ok(())
     }
 
-    async fn playlist_change(&self) -> fdo::Result<()> {
-        let mut playlist_empty = self.playlist_empty.lock().await;
-        if *playlist_empty {
-            *playlist_empty = false;
-            self.tx.send(Command::PlaylistIsEmpty).await.unwrap();
-        } else {
-            self.tx.send(Command::ReloadPlaylist).await.unwrap();
-        }
-        Ok(())
+    /// 添加歌曲, 参数为歌曲数组的 JSON, 返回新增的歌曲数量
+    async fn add_tracks(&self, tracks: String) -> fdo::Result<u32> {
+        let import = SourceImport {
+            tracks: parse_json(&tracks)?,
+            ..SourceImport::default()
+        };
+        self.change_playlist(PlaylistChange::ImportSource(Box::new(import)))
+            .await
     }
 
-    async fn playlist_is_empty(&self) -> fdo::Result<()> {
-        self.tx.send(Command::Stop).await.unwrap();
-        let mut playlist_empty = self.playlist_empty.lock().await;
-        *playlist_empty = true;
-        update_current_play_tracks(None, Vec::new()).await.unwrap();
-        Ok(())
+    /// 导入收藏夹、合集等来源, 参数为 `SourceImport` 的 JSON, 返回新增的歌曲数量
+    async fn import_source(&self, import: String) -> fdo::Result<u32> {
+        let import: SourceImport = parse_json(&import)?;
+        self.change_playlist(PlaylistChange::ImportSource(Box::new(import)))
+            .await
+    }
+
+    /// 修改歌曲的评分、标签等信息, 参数为 `TrackPatch` 数组的 JSON, 返回修改的歌曲数量
+    async fn update_tracks(&self, patches: String) -> fdo::Result<u32> {
+        let patches: Vec<TrackPatch> = parse_json(&patches)?;
+        self.change_playlist(PlaylistChange::UpdateTracks(patches))
+            .await
+    }
+
+    /// 删除歌曲和导入来源, 参数为 `TrackRemoval` 的 JSON, 返回删除的数量
+    async fn remove_tracks(&self, removal: String) -> fdo::Result<u32> {
+        let removal: TrackRemoval = parse_json(&removal)?;
+        self.change_playlist(PlaylistChange::RemoveTracks(removal))
+            .await
+    }
+
+    /// 一次应用多个修改, 参数为 `PlaylistChange` 数组的 JSON, 返回受影响的数量
+    async fn apply_changes(&self, changes: String) -> fdo::Result<u32> {
+        let changes: Vec<PlaylistChange> = parse_json(&changes)?;
+        self.change_playlist(PlaylistChange::Batch(changes)).await
+    }
+
+    /// 歌曲列表中所有歌曲的 JSON
+    async fn list_tracks(&self) -> fdo::Result<String> {
+        let playlist = PLAYLIST.read().await;
+        let playlist = playlist
+            .as_ref()
+            .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+        serde_json::to_string(&playlist.tracks).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// 当前歌曲及其播放进度 (秒)
+    async fn position(&self) -> fdo::Result<(TrackId, f64)> {
+        let track = get_current_track()
+            .await
+            .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+        Ok((track.id(), self.audio.position().unwrap_or_default()))
+    }
+
+    /// 当前播放进度对应的歌词, 没有歌词时为空字符串
+    async fn current_lyric(&self) -> String {
+        self.audio.current_lyric().await
     }
 }
 
 pub async fn run_dbus_server(
     command_sender: mpsc::Sender<Command>,
     stop_signal: watch::Sender<()>,
+    audio: Audio,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let player_dbus = PlayerDBus {
         tx: command_sender,
         stop_signal: stop_signal.clone(),
-        playlist_empty: Arc::new(Mutex::new(false)),
+        audio,
     };
 
-    let _connection = ConnectionBuilder::session()?
+    let mpris_player = MprisPlayer {
+        tx: player_dbus.tx.clone(),
+        audio: player_dbus.audio.clone(),
+    };
+    let connection = ConnectionBuilder::session()?
         .name("org.rosesong.Player")?
+        .name(MPRIS_NAME)?
         .serve_at("/org/rosesong/Player", player_dbus)?
+        .serve_at(MPRIS_PATH, MprisRoot)?
+        .serve_at(MPRIS_PATH, mpris_player)?
         .build()
         .await?;
+    start_mpris_notifier(&connection).await?;
 
     let mut stop_receiver = stop_signal.subscribe();
 
@@ -1,17 +1,19 @@
 mod bilibili;
 mod dbus;
+mod mpris;
 mod player;
-mod temp_dbus;
 
 use crate::player::Audio;
-use flexi_logger::{Cleanup, Criterion, Duplicate, FileSpec, Logger, Naming};
+use flexi_logger::{Cleanup, Criterion, Duplicate, FileSpec, Logger, LoggerHandle, Naming};
 use log::{error, info, warn};
-use player::playlist::{load, CURRENT_PLAY_INFO};
+use player::playlist::{load, CONFIG, CURRENT_PLAY_INFO};
+use rosesong::config::{load_config, Config};
 use rosesong::error::AppError;
 use rosesong::model::PlayMode;
-use rosesong::utils::{init_dir, is_playlist_empty, logs_dir};
+use rosesong::utils::{config_file, init_dir, logs_dir, migrate_legacy_dirs, LegacyMigration};
 use std::process;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tikv_jemallocator::Jemalloc;
 use tokio::{
     sync::{mpsc, watch, Mutex},
@@ -23,10 +25,16 @@ static GLOBAL: Jemalloc = Jemalloc;
 
 #[tokio::main]
 async fn main() -> Result<(), AppError> {
-    // init dir
+    // init dir, 迁移结果在日志初始化后记录
+    let legacy_migrations = migrate_legacy_dirs()?;
     init_dir().await?;
+    // 配置文件有误时使用默认配置启动, 日志初始化后再记录错误
+    let (config, config_error) = match load_config() {
+        Ok(config) => (config, None),
+        Err(e) => (Config::default(), Some(e)),
+    };
     // Logger setup
-    Logger::try_with_str("info")?
+    let logger = Logger::try_with_str(&config.log.level)?
         .format(|w, _, record| {
             write!(
                 w,
@@ -40,34 +48,38 @@ async fn main() -> Result<(), AppError> {
         .log_to_file(FileSpec::default().directory(logs_dir()?))
         // .duplicate_to_stdout(Duplicate::All) // for debug
         .rotate(
-            Criterion::Size(1_000_000),
+            Criterion::Size(config.log.rotate_size),
             Naming::Timestamps,
-            Cleanup::KeepLogFiles(3),
+            Cleanup::KeepLogFiles(config.log.keep_files),
         )
         .duplicate_to_stderr(Duplicate::None)
         .start()?;
-
-    // Check if the playlist is empty
-    {
-        let is_empty = is_playlist_empty().await?;
-        if is_empty {
-            warn!("Current playlist is empty");
-            let (stop_sender, stop_receiver) = watch::channel(());
-            // wait for cli to add song, and then this temp dbus listener will stop
-            let _ = start_temp_dbus_listener(stop_sender).await;
-            wait_for_stop_signal(stop_receiver).await;
-            // if playlist is still empty, shutdown process
-            if is_playlist_empty().await? {
-                process::exit(0);
+    for migration in legacy_migrations {
+        match migration {
+            LegacyMigration::Moved { from, to } => {
+                info!("migrated {} to {}", from.display(), to.display());
+            }
+            LegacyMigration::Skipped { from, to } => {
+                warn!(
+                    "{} already exists, skip migrating {}",
+                    to.display(),
+                    from.display()
+                );
             }
         }
     }
+    if let Some(e) = config_error {
+        error!("Invalid config, using defaults: {}", e);
+    }
+    *CONFIG.write().await = config;
+    start_config_watcher(logger);
 
     info!("loading init");
     load().await?;
     let (stop_sender, stop_receiver) = watch::channel(());
     let play_mode = CURRENT_PLAY_INFO.read().await.play_mode;
-    let _audio_player = start_player_and_dbus_listener(play_mode, &stop_sender)?;
+    let timeout = Duration::from_secs(CONFIG.read().await.network.timeout_secs);
+    let _audio_player = start_player_and_dbus_listener(play_mode, timeout, &stop_sender)?;
     wait_for_stop_signal(stop_receiver).await;
     process::exit(0);
 }
@@ -76,40 +88,21 @@ async fn wait_for_stop_signal(mut stop_receiver: watch::Receiver<()>) {
     stop_receiver.changed().await.unwrap();
 }
 
-async fn start_temp_dbus_listener(
-    stop_signal: watch::Sender<()>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let stop_receiver = stop_signal.subscribe();
-
-    task::spawn({
-        let stop_signal = stop_signal.clone();
-        async move {
-            let result = temp_dbus::run_temp_dbus_server(stop_signal).await;
-            if let Err(e) = result {
-                error!("Temp DBus listener error: {}", e);
-            }
-        }
-    });
-
-    // Wait for the stop signal
-    wait_for_stop_signal(stop_receiver).await;
-
-    Ok(())
-}
-
 fn start_player_and_dbus_listener(
     play_mode: PlayMode,
+    timeout: Duration,
     stop_signal: &watch::Sender<()>,
 ) -> Result<Audio, AppError> {
     let (command_sender, command_receiver) = mpsc::channel(1);
 
-    let audio_player = Audio::new(play_mode, Arc::new(Mutex::new(command_receiver)))?;
+    let audio_player = Audio::new(play_mode, Arc::new(Mutex::new(command_receiver)), timeout)?;
 
     task::spawn({
         let command_sender = command_sender.clone();
         let stop_signal = stop_signal.clone();
+        let audio_player = audio_player.clone();
         async move {
-            let _ = dbus::run_dbus_server(command_sender, stop_signal).await;
+            let _ = dbus::run_dbus_server(command_sender, stop_signal, audio_player).await;
         }
     });
 
@@ -119,9 +112,43 @@ fn start_player_and_dbus_listener(
             audio_player.play_playlist().await.unwrap();
             #[allow(clippy::cast_precision_loss)]
             let volume = CURRENT_PLAY_INFO.read().await.volume as f64 / 100.0;
-            audio_player.fade_volume(0.0, volume, 3);
+            let fade_in_secs = CONFIG.read().await.player.fade_in_secs;
+            audio_player.fade_volume(0.0, volume, fade_in_secs);
         }
     });
 
     Ok(audio_player)
 }
+
+fn config_modified_time() -> Option<SystemTime> {
+    config_file().ok()?.metadata().ok()?.modified().ok()
+}
+
+// 定时检查配置文件的修改时间, 文件变化后重新加载配置, 新配置有误时继续使用旧配置
+fn start_config_watcher(logger: LoggerHandle) {
+    task::spawn(async move {
+        let mut last_modified = config_modified_time();
+        let mut interval = tokio::time::interval(Duration::from_secs(2));
+        loop {
+            interval.tick().await;
+            let modified = config_modified_time();
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+            match load_config() {
+                Ok(config) => {
+                    let mut current = CONFIG.write().await;
+                    if config.log.level != current.log.level {
+                        if let Err(e) = logger.parse_new_spec(&config.log.level) {
+                            error!("Failed to change log level: {}", e);
+                        }
+                    }
+                    *current = config;
+                    info!("Config reloaded");
+                }
+                Err(e) => error!("Invalid config, keep using the previous one: {}", e),
+            }
+        }
+    });
+}
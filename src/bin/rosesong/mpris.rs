@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use log::error;
+use rosesong::cover::{art_url, cover_file};
+use rosesong::model::Track;
+use tokio::sync::mpsc;
+use tokio::task;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{fdo, interface, Connection};
+
+use crate::player::{playlist::get_current_track, Audio, Command};
+
+pub const MPRIS_NAME: &str = "org.mpris.MediaPlayer2.rosesong";
+pub const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// MPRIS 的 org.mpris.MediaPlayer2 接口, rosesong 没有窗口, 只提供名称
+pub struct MprisRoot;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl MprisRoot {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "RoseSong"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// MPRIS 的 org.mpris.MediaPlayer2.Player 接口, 供桌面环境的媒体控件显示当前歌曲和控制播放
+pub struct MprisPlayer {
+    pub tx: mpsc::Sender<Command>,
+    pub audio: Audio,
+}
+
+impl MprisPlayer {
+    async fn send(&self, command: Command) -> fdo::Result<()> {
+        self.tx
+            .send(command)
+            .await
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl MprisPlayer {
+    async fn play(&self) -> fdo::Result<()> {
+        self.send(Command::Play).await
+    }
+
+    async fn pause(&self) -> fdo::Result<()> {
+        self.send(Command::Pause).await
+    }
+
+    async fn play_pause(&self) -> fdo::Result<()> {
+        if self.audio.playback_status() == "Playing" {
+            self.send(Command::Pause).await
+        } else {
+            self.send(Command::Play).await
+        }
+    }
+
+    async fn stop(&self) -> fdo::Result<()> {
+        self.send(Command::Stop).await
+    }
+
+    async fn next(&self) -> fdo::Result<()> {
+        self.send(Command::Next).await
+    }
+
+    async fn previous(&self) -> fdo::Result<()> {
+        self.send(Command::Previous).await
+    }
+
+    #[allow(clippy::unused_self)]
+    fn seek(&self, _offset: i64) {}
+
+    #[allow(clippy::unused_self)]
+    fn set_position(&self, _track_id: ObjectPath<'_>, _position: i64) {}
+
+    #[allow(clippy::unused_self)]
+    fn open_uri(&self, _uri: &str) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported("OpenUri".to_string()))
+    }
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        self.audio.playback_status()
+    }
+
+    #[zbus(property)]
+    async fn metadata(&self) -> HashMap<String, OwnedValue> {
+        match get_current_track().await {
+            Ok(track) => metadata(&track),
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    /// 播放进度 (微秒)
+    #[allow(clippy::cast_possible_truncation)]
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        (self.audio.position().unwrap_or_default() * 1_000_000.0) as i64
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        false
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+fn owned(value: Value<'_>) -> Option<OwnedValue> {
+    OwnedValue::try_from(value).ok()
+}
+
+// 歌曲的 MPRIS 元数据, 封面已缓存时提供 mpris:artUrl
+fn metadata(track: &Track) -> HashMap<String, OwnedValue> {
+    let mut metadata = HashMap::new();
+    let track_id = format!("/org/rosesong/track/{}_{}", track.bvid, track.cid);
+    let mut entries = vec![
+        (
+            "mpris:trackid",
+            ObjectPath::try_from(track_id).ok().map(Value::from),
+        ),
+        ("xesam:title", Some(Value::from(track.title.clone()))),
+        ("xesam:artist", Some(Value::from(vec![track.owner.clone()]))),
+        (
+            "mpris:length",
+            track
+                .duration
+                .and_then(|d| i64::try_from(d * 1_000_000).ok())
+                .map(Value::from),
+        ),
+    ];
+    if let Ok(path) = cover_file(track) {
+        if path.exists() {
+            entries.push(("mpris:artUrl", Some(Value::from(art_url(&path)))));
+        }
+    }
+    for (key, value) in entries {
+        if let Some(value) = value.and_then(owned) {
+            metadata.insert(key.to_string(), value);
+        }
+    }
+    metadata
+}
+
+// 切换歌曲、封面缓存完成或播放状态变化后发出 PropertiesChanged 信号, 与配置文件一样定时检查
+pub async fn start_mpris_notifier(connection: &Connection) -> zbus::Result<()> {
+    let player = connection
+        .object_server()
+        .interface::<_, MprisPlayer>(MPRIS_PATH)
+        .await?;
+    task::spawn(async move {
+        let mut last = None;
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            let current = get_current_track().await.ok().map(|track| {
+                let has_cover = cover_file(&track).is_ok_and(|path| path.exists());
+                (track.id(), has_cover)
+            });
+            let iface = player.get().await;
+            let status = iface.audio.playback_status();
+            let state = (current, status);
+            if last.as_ref() == Some(&state) {
+                continue;
+            }
+            let ctxt = player.signal_context();
+            if last.as_ref().is_none_or(|(track, _)| *track != state.0) {
+                if let Err(e) = iface.metadata_changed(ctxt).await {
+                    error!("Failed to notify MPRIS metadata: {}", e);
+                }
+            }
+            if last.as_ref().is_none_or(|(_, s)| *s != status) {
+                if let Err(e) = iface.playback_status_changed(ctxt).await {
+                    error!("Failed to notify MPRIS playback status: {}", e);
+                }
+            }
+            drop(iface);
+            last = Some(state);
+        }
+    });
+    Ok(())
+}
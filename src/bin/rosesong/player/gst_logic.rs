@@ -1,16 +1,19 @@
+use crate::player::history::{record_finish, record_start};
+use crate::player::lyrics::current_lyric_line;
 use crate::player::network::{fetch_and_verify_audio_url, set_pipeline_uri_with_headers};
 use crate::player::playlist::{
-    get_current_track, load, move_to_next_track, move_to_previous_track, set_current_track_index,
-    PLAYLIST,
+    get_current_track, jump_to_track_index, load, mark_unavailable, move_to_next_track,
+    move_to_previous_track, set_current_track_index, CONFIG, PLAYLIST,
 };
 use futures_util::stream::StreamExt;
 use gstreamer::prelude::*;
 use gstreamer::MessageView;
 use gstreamer::Pipeline;
-use log::{error, info};
-use reqwest::{Client, ClientBuilder};
+use log::{error, info, warn};
+use rosesong::bili_client::BiliClient;
+use rosesong::cover::cache_cover;
 use rosesong::error::AppError;
-use rosesong::model::PlayMode;
+use rosesong::model::{CollectionKind, PlayMode, TrackId, TrackSelection, UnavailableReason};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, Mutex, RwLock};
@@ -20,8 +23,11 @@ use super::playlist::{update_current_play_tracks, CURRENT_PLAY_INFO};
 
 pub enum Command {
     Play,
-    PlayBvid(String),
+    PlayTrack(TrackId),
     PlaySid(String),
+    PlaySeries(String),
+    PlayTag(String),
+    PlaySmart(String),
     PlayAll,
     Pause,
     Next,
@@ -30,14 +36,13 @@ pub enum Command {
     SetVolume(String),
     SetPlayMode(PlayMode),
     ReloadPlaylist,
-    PlaylistIsEmpty,
 }
 
 #[derive(Clone, Debug)]
 pub struct Audio {
     pipeline: Arc<Pipeline>,
     volume_ele: Arc<gstreamer::Element>,
-    client: Arc<Client>,
+    client: Arc<BiliClient>,
     play_mode: Arc<RwLock<PlayMode>>,
     command_receiver: Arc<Mutex<mpsc::Receiver<Command>>>,
     eos_sender: mpsc::Sender<()>,
@@ -47,6 +52,7 @@ impl Audio {
     pub fn new(
         play_mode: PlayMode,
         command_receiver: Arc<Mutex<mpsc::Receiver<Command>>>,
+        timeout: Duration,
     ) -> Result<Self, AppError> {
         gstreamer::init().map_err(|e| AppError::Init(e.to_string()))?;
         let pipeline = Arc::new(gstreamer::Pipeline::new());
@@ -56,11 +62,7 @@ impl Audio {
                 .build()
                 .map_err(|_| AppError::Element("Failed to create volume Element".to_string()))?,
         );
-        let client = Arc::new(
-            ClientBuilder::new()
-                .timeout(Duration::from_secs(5))
-                .build()?,
-        );
+        let client = Arc::new(BiliClient::with_timeout(timeout)?);
         let (eos_sender, eos_receiver) = mpsc::channel(1);
 
         info!("GStreamer created successfully.");
@@ -90,10 +92,23 @@ impl Audio {
         task::spawn(async move {
             while let Some(()) = eos_receiver.recv().await {
                 info!("Track finished playing. Handling EOS...");
+                if let Err(e) = record_finish(&pipeline, false).await {
+                    error!("Failed to record play history: {}", e);
+                }
 
-                if let Err(e) = move_to_next_track().await {
-                    error!("Error moving to next track: {}", e);
-                    continue;
+                match move_to_next_track().await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        info!("All tracks have been played, stop playback");
+                        if let Err(e) = pipeline.set_state(gstreamer::State::Null) {
+                            error!("Failed to stop: {}", e);
+                        }
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Error moving to next track: {}", e);
+                        continue;
+                    }
                 }
 
                 if let Err(e) = play_track(&pipeline, &volume_ele_, &client).await {
@@ -102,9 +117,39 @@ impl Audio {
             }
         });
     }
+    /// 当前歌曲的播放进度 (秒), 没有在播放时返回 None
+    pub fn position(&self) -> Option<f64> {
+        self.pipeline
+            .query_position::<gstreamer::ClockTime>()
+            .map(|pos| pos.mseconds() as f64 / 1000.0)
+    }
+
+    /// 当前播放进度对应的歌词
+    pub async fn current_lyric(&self) -> String {
+        let Ok(track) = get_current_track().await else {
+            return String::new();
+        };
+        let position = self.position().unwrap_or_default();
+        current_lyric_line(&self.client, &track, position).await
+    }
+
+    /// 播放状态, 对应 MPRIS 的 PlaybackStatus
+    pub fn playback_status(&self) -> &'static str {
+        match self.pipeline.current_state() {
+            gstreamer::State::Playing => "Playing",
+            gstreamer::State::Paused => "Paused",
+            _ => "Stopped",
+        }
+    }
+
     /// 渐变调整音量（单位：秒）
     pub fn fade_volume(&self, start: f64, target: f64, duration_sec: u8) {
-        let all_step = duration_sec * 10;
+        if duration_sec == 0 {
+            self.volume_ele.set_property("volume", target);
+            return;
+        }
+        // 每 100 毫秒调整一次
+        let all_step = u32::from(duration_sec) * 10;
         let delta = (target - start) / f64::from(all_step);
         for step in 0..=all_step {
             let new_vol = start + delta * f64::from(step);
@@ -131,6 +176,11 @@ impl Audio {
             &eos_sender,
         );
 
+        // 歌曲列表为空时等待添加歌曲
+        if CURRENT_PLAY_INFO.read().await.current_tracks.is_empty() {
+            info!("Current playlist is empty, waiting for tracks");
+            return Ok(());
+        }
         play_track(&self.pipeline, &self.volume_ele.clone(), &self.client).await?;
         Ok(())
     }
@@ -172,7 +222,7 @@ impl Audio {
         command_receiver: Arc<Mutex<mpsc::Receiver<Command>>>,
         pipeline: Arc<Pipeline>,
         volume_ele: Arc<gstreamer::Element>,
-        client: Arc<Client>,
+        client: Arc<BiliClient>,
         play_mode: Arc<RwLock<PlayMode>>,
         _eos_sender: &mpsc::Sender<()>,
     ) {
@@ -187,22 +237,58 @@ impl Audio {
                                 error!("Failed to play: {}", e);
                             }
                         }
-                        Command::PlayBvid(new_bvid) => {
-                            info!("Play {}", new_bvid);
+                        Command::PlayTrack(id) => {
+                            info!("Play {}", id);
                             if let Err(e) =
-                                handle_play_bvid(&new_bvid, &pipeline, &volume_ele, &client).await
+                                handle_play_track(&id, &pipeline, &volume_ele, &client).await
                             {
                                 error!("Failed to play track: {}", e);
                             }
                         }
                         Command::PlaySid(new_sid) => {
                             info!("Play {}", new_sid);
-                            if let Err(e) =
-                                handle_play_sid(&new_sid, &pipeline, &volume_ele, &client).await
+                            if let Err(e) = handle_play_season(
+                                CollectionKind::Season,
+                                &new_sid,
+                                &pipeline,
+                                &volume_ele,
+                                &client,
+                            )
+                            .await
                             {
                                 error!("Failed to play season: {}", e);
                             }
                         }
+                        Command::PlaySeries(id) => {
+                            info!("Play series {}", id);
+                            if let Err(e) = handle_play_season(
+                                CollectionKind::Series,
+                                &id,
+                                &pipeline,
+                                &volume_ele,
+                                &client,
+                            )
+                            .await
+                            {
+                                error!("Failed to play series: {}", e);
+                            }
+                        }
+                        Command::PlayTag(tag) => {
+                            info!("Play tag {}", tag);
+                            if let Err(e) =
+                                handle_play_tag(&tag, &pipeline, &volume_ele, &client).await
+                            {
+                                error!("Failed to play tag: {}", e);
+                            }
+                        }
+                        Command::PlaySmart(name) => {
+                            info!("Play smart playlist {}", name);
+                            if let Err(e) =
+                                handle_play_smart(&name, &pipeline, &volume_ele, &client).await
+                            {
+                                error!("Failed to play smart playlist: {}", e);
+                            }
+                        }
                         Command::PlayAll => {
                             info!("Play all song");
                             if let Err(e) = handle_play_all(&pipeline, &volume_ele, &client).await {
@@ -231,6 +317,10 @@ impl Audio {
                             }
                         }
                         Command::Stop => {
+                            // 停止时歌曲没有播放完, 不计入完整播放
+                            if let Err(e) = record_finish(&pipeline, true).await {
+                                error!("Failed to record play history: {}", e);
+                            }
                             if let Err(e) = pipeline.set_state(gstreamer::State::Null) {
                                 error!("Failed to stop: {}", e);
                             }
@@ -253,13 +343,6 @@ impl Audio {
                                 error!("Failed to reload playlist: {}", e);
                             }
                         }
-                        Command::PlaylistIsEmpty => {
-                            if let Err(e) =
-                                handle_playlist_is_empty(&pipeline, &volume_ele, &client).await
-                            {
-                                error!("Failed to play track after reloading playlist: {}", e);
-                            }
-                        }
                     }
                 }
             }
@@ -267,63 +350,110 @@ impl Audio {
     }
 }
 
-async fn handle_play_bvid(
-    new_bvid: &str,
+async fn handle_play_track(
+    id: &TrackId,
     pipeline: &Pipeline,
     volume_ele: &gstreamer::Element,
-    client: &Client,
+    client: &BiliClient,
 ) -> Result<(), AppError> {
     let new_index = {
         let current_play_info = CURRENT_PLAY_INFO.read().await;
-        current_play_info.find_track_index(new_bvid)
+        current_play_info.find_track_index(id)
     };
 
     if let Some(index) = new_index {
-        set_current_track_index(index).await.ok();
+        jump_to_track_index(index).await.ok();
     } else {
         let all_tracks = {
             let playlist = PLAYLIST.read().await;
             let playlist = playlist.as_ref().unwrap();
             playlist.tracks.clone()
         };
-        let global_index = all_tracks.iter().position(|t| t.bvid == new_bvid);
+        let global_index = all_tracks.iter().position(|t| t.has_id(id));
         if let Some(global_index) = global_index {
             info!("当前播放合集中未找到歌曲，切换为播放全部歌曲");
-            update_current_play_tracks(None, all_tracks).await?;
+            update_current_play_tracks(TrackSelection::All, all_tracks).await?;
             set_current_track_index(global_index).await.ok();
         } else {
-            error!("Track with bvid {} not found in the playlist", new_bvid);
+            error!("Track {} not found in the playlist", id);
         }
     }
 
     play_track(pipeline, volume_ele, client).await
 }
 
-async fn handle_play_sid(
-    new_sid: &str,
+async fn handle_play_season(
+    kind: CollectionKind,
+    id: &str,
     pipeline: &Pipeline,
     volume_ele: &gstreamer::Element,
-    client: &Client,
+    client: &BiliClient,
 ) -> Result<(), AppError> {
     let new_play_tracks = {
         let playlist = PLAYLIST.read().await;
         let playlist = playlist.as_ref().unwrap();
-        playlist.find_tracks_in_season(new_sid)
+        playlist.find_tracks_in_season(kind, id)
     };
 
     if new_play_tracks.is_empty() {
-        error!("Tracks with sid {} not found in the playlist", new_sid);
+        error!("Tracks in {:?} {} not found in the playlist", kind, id);
     } else {
-        update_current_play_tracks(Some(new_sid.to_string()), new_play_tracks).await?;
+        let selection = match kind {
+            CollectionKind::Season => TrackSelection::Season(id.to_string()),
+            CollectionKind::Series => TrackSelection::Series(id.to_string()),
+        };
+        update_current_play_tracks(selection, new_play_tracks).await?;
+    }
+
+    play_track(pipeline, volume_ele, client).await
+}
+
+async fn handle_play_tag(
+    tag: &str,
+    pipeline: &Pipeline,
+    volume_ele: &gstreamer::Element,
+    client: &BiliClient,
+) -> Result<(), AppError> {
+    let new_play_tracks = {
+        let playlist = PLAYLIST.read().await;
+        let playlist = playlist.as_ref().unwrap();
+        playlist.find_tracks_with_tag(tag)
+    };
+
+    if new_play_tracks.is_empty() {
+        error!("Tracks with tag {} not found in the playlist", tag);
+        return Ok(());
     }
+    update_current_play_tracks(TrackSelection::Tag(tag.to_string()), new_play_tracks).await?;
+    play_track(pipeline, volume_ele, client).await
+}
 
+async fn handle_play_smart(
+    name: &str,
+    pipeline: &Pipeline,
+    volume_ele: &gstreamer::Element,
+    client: &BiliClient,
+) -> Result<(), AppError> {
+    // 重新计算智能列表, 保证列表内容是最新的
+    load().await?;
+    let new_play_tracks = {
+        let playlist = PLAYLIST.read().await;
+        let playlist = playlist.as_ref().unwrap();
+        playlist.find_tracks_in_smart(name)
+    };
+
+    if new_play_tracks.is_empty() {
+        error!("Smart playlist {} is empty or not found", name);
+        return Ok(());
+    }
+    update_current_play_tracks(TrackSelection::Smart(name.to_string()), new_play_tracks).await?;
     play_track(pipeline, volume_ele, client).await
 }
 
 async fn handle_play_all(
     pipeline: &Pipeline,
     volume_ele: &gstreamer::Element,
-    client: &Client,
+    client: &BiliClient,
 ) -> Result<(), AppError> {
     let current_track = {
         let current_play_info = CURRENT_PLAY_INFO.read().await;
@@ -338,19 +468,16 @@ async fn handle_play_all(
     if new_play_tracks.is_empty() {
         error!("Tracks not found in the playlist");
     } else {
-        update_current_play_tracks(None, new_play_tracks.clone()).await?;
+        update_current_play_tracks(TrackSelection::All, new_play_tracks.clone()).await?;
     }
 
     if let Some(current_track) = current_track {
-        let bvid = current_track.bvid;
-        let new_index = new_play_tracks.iter().position(|t| t.bvid == bvid);
+        let id = current_track.id();
+        let new_index = new_play_tracks.iter().position(|t| t.has_id(&id));
         if let Some(index) = new_index {
             set_current_track_index(index).await.ok();
         } else {
-            error!(
-                "Track with bvid {} not found in the playlist, play next song",
-                bvid
-            );
+            error!("Track {} not found in the playlist, play next song", id);
             play_track(pipeline, volume_ele, client).await?;
         }
     } else {
@@ -362,17 +489,28 @@ async fn handle_play_all(
 async fn handle_next_track(
     pipeline: &Pipeline,
     volume_ele: &gstreamer::Element,
-    client: &Client,
+    client: &BiliClient,
 ) -> Result<(), AppError> {
-    move_to_next_track().await?;
+    if CURRENT_PLAY_INFO.read().await.current_tracks.is_empty() {
+        info!("Current playlist is empty, ignore next");
+        return Ok(());
+    }
+    if !move_to_next_track().await? {
+        info!("Already at the last track");
+        return Ok(());
+    }
     play_track(pipeline, volume_ele, client).await
 }
 
 async fn handle_previous_track(
     pipeline: &Pipeline,
     volume_ele: &gstreamer::Element,
-    client: &Client,
+    client: &BiliClient,
 ) -> Result<(), AppError> {
+    if CURRENT_PLAY_INFO.read().await.current_tracks.is_empty() {
+        info!("Current playlist is empty, ignore previous");
+        return Ok(());
+    }
     move_to_previous_track().await?;
     play_track(pipeline, volume_ele, client).await
 }
@@ -383,9 +521,10 @@ async fn handle_volume_change(
 ) -> Result<(), AppError> {
     let current_volume = volume_ele.property::<f64>("volume");
     let current_volume = (current_volume * 100.0).round() / 100.0;
+    let step = f64::from(CONFIG.read().await.player.volume_step) / 100.0;
     let new_volume = match vol.as_str() {
         "up" => {
-            let vol = ((current_volume + 0.05) * 100.0).round() / 100.0;
+            let vol = ((current_volume + step) * 100.0).round() / 100.0;
             if vol <= 1.0 {
                 vol
             } else {
@@ -393,7 +532,7 @@ async fn handle_volume_change(
             }
         }
         "down" => {
-            let vol = ((current_volume - 0.05) * 100.0).round() / 100.0;
+            let vol = ((current_volume - step) * 100.0).round() / 100.0;
             if vol > 0.0 {
                 vol
             } else {
@@ -428,7 +567,7 @@ async fn handle_change_mode(
 async fn handle_reload_playlist(
     pipeline: &Pipeline,
     volume_ele: &gstreamer::Element,
-    client: &Client,
+    client: &BiliClient,
 ) -> Result<(), AppError> {
     let current_track = get_current_track().await;
     load().await?;
@@ -438,44 +577,48 @@ async fn handle_reload_playlist(
     };
 
     if tracks.is_empty() {
-        // pause playback
+        // 歌曲列表被清空, 停止播放
+        if let Err(e) = record_finish(pipeline, true).await {
+            error!("Failed to record play history: {}", e);
+        }
         if let Err(e) = pipeline.set_state(gstreamer::State::Null) {
             error!("Failed to stop: {}", e);
         }
         return Ok(());
     }
 
-    if let Ok(current_track) = current_track {
-        if let Some(new_index) = tracks.iter().position(|t| t.bvid == current_track.bvid) {
-            set_current_track_index(new_index).await.ok();
-            info!(
-                "Current track found in the new playlist, index set to {}",
-                new_index
-            );
-        } else {
-            info!("Current track not found in the new playlist, resetting play");
+    match current_track {
+        Ok(current_track) => {
+            if let Some(new_index) = tracks.iter().position(|t| t.has_id(&current_track.id())) {
+                set_current_track_index(new_index).await.ok();
+                info!(
+                    "Current track found in the new playlist, index set to {}",
+                    new_index
+                );
+            } else {
+                info!("Current track not found in the new playlist, resetting play");
+                play_track(pipeline, volume_ele, client).await?;
+            }
+        }
+        Err(_) => {
+            // 之前歌曲列表为空, 添加歌曲后从头开始播放
+            info!("Playlist was empty, start playing");
+            set_current_track_index(0).await.ok();
             play_track(pipeline, volume_ele, client).await?;
         }
     }
     Ok(())
 }
 
-async fn handle_playlist_is_empty(
-    pipeline: &Pipeline,
-    volume_ele: &gstreamer::Element,
-    client: &Client,
-) -> Result<(), AppError> {
-    load().await?;
-    info!("Set track");
-    set_current_track_index(0).await.ok();
-    play_track(pipeline, volume_ele, client).await
-}
-
 async fn play_track(
     pipeline: &Pipeline,
     volume_ele: &gstreamer::Element,
-    client: &Client,
+    client: &BiliClient,
 ) -> Result<(), AppError> {
+    // 上一首还没有播放完就被切换
+    if let Err(e) = record_finish(pipeline, true).await {
+        error!("Failed to record play history: {}", e);
+    }
     pipeline
         .set_state(gstreamer::State::Null)
         .map_err(|_| AppError::State("Failed to set pipeline to Null".to_string()))?;
@@ -491,7 +634,9 @@ async fn play_track(
         .map_err(|_| AppError::State("Failed to set pipeline to Ready".to_string()))?;
 
     // TODO: 网络不好，一直在重试时，如果这时候发起 next 命令，需要将此循环中断
-    let mut retries = 5;
+    let mut retries = CONFIG.read().await.network.play_retries;
+    // 已失效的歌曲直接跳过, 不计入重试次数, 但最多跳过一轮
+    let mut skipped = 0;
     loop {
         if retries == 0 {
             return Err(AppError::Fetch(
@@ -499,13 +644,50 @@ async fn play_track(
             ));
         }
         let track = get_current_track().await?;
-        if let Ok(url) = fetch_and_verify_audio_url(client, &track.bvid, &track.cid).await {
-            set_pipeline_uri_with_headers(pipeline, volume_ele.clone(), &url).await?;
-            break;
+        if track.unavailable.is_none() {
+            match fetch_and_verify_audio_url(client, &track.bvid, &track.cid).await {
+                Ok(url) => {
+                    set_pipeline_uri_with_headers(pipeline, volume_ele.clone(), &url).await?;
+                    record_start(&track).await;
+                    // 提前缓存封面, 缓存完成后通过 MPRIS 的 mpris:artUrl 提供
+                    let http = client.http().clone();
+                    task::spawn(async move {
+                        let cover = match PLAYLIST.read().await.as_ref() {
+                            Ok(playlist) => playlist.cover_of(&track).to_string(),
+                            Err(_) => track.cover.clone(),
+                        };
+                        if let Err(e) = cache_cover(&http, &track, &cover).await {
+                            error!("Failed to cache cover of {}: {}", track.bvid, e);
+                        }
+                    });
+                    break;
+                }
+                Err(e) => match UnavailableReason::from_error(&e) {
+                    Some(reason) => {
+                        warn!("{} is unavailable ({}), skip it", track.bvid, e);
+                        if let Err(e) = mark_unavailable(&track.bvid, reason).await {
+                            error!("Failed to mark {} as unavailable: {}", track.bvid, e);
+                        }
+                    }
+                    None => {
+                        if matches!(e, AppError::NotLoggedIn) {
+                            warn!("Not logged in or session expired, run rsg login");
+                        }
+                        log::info!("Failed to fetch audio URL, play next song");
+                        retries -= 1;
+                    }
+                },
+            }
+        }
+        skipped += 1;
+        if skipped > CURRENT_PLAY_INFO.read().await.current_tracks.len() {
+            return Err(AppError::Fetch("No playable tracks left".to_string()));
+        }
+        if !move_to_next_track().await? {
+            return Err(AppError::Fetch(
+                "No more tracks to play after fetch failure".to_string(),
+            ));
         }
-        log::info!("Failed to fetch audio URL, play next song");
-        move_to_next_track().await?;
-        retries -= 1;
     }
 
     pipeline
@@ -0,0 +1,43 @@
+use gstreamer::prelude::ElementExtManual;
+use gstreamer::Pipeline;
+use log::error;
+use rosesong::{
+    error::AppError,
+    history::{append_play_record, PlayRecord},
+    model::Track,
+};
+use std::sync::LazyLock;
+use tokio::sync::Mutex;
+
+use super::playlist::CURRENT_PLAY_INFO;
+
+/// 正在播放的歌曲对应的开始记录
+static PLAYING_RECORD: LazyLock<Mutex<Option<PlayRecord>>> = LazyLock::new(|| Mutex::new(None));
+
+pub async fn record_start(track: &Track) {
+    let record = PlayRecord::start(track);
+    if let Err(e) = append_play_record(&record).await {
+        error!("Failed to append play history: {}", e);
+    }
+    *PLAYING_RECORD.lock().await = Some(record);
+}
+
+/// 记录当前歌曲播放结束, 需要在 pipeline 重置之前调用以获取实际播放进度
+pub async fn record_finish(pipeline: &Pipeline, skipped: bool) -> Result<(), AppError> {
+    let Some(record) = PLAYING_RECORD.lock().await.take() else {
+        return Ok(());
+    };
+    let listened_secs = pipeline
+        .query_position::<gstreamer::ClockTime>()
+        .map_or(0, gstreamer::ClockTime::seconds);
+    if !skipped {
+        CURRENT_PLAY_INFO
+            .write()
+            .await
+            .play_counts
+            .entry(record.track_id())
+            .and_modify(|c| *c += 1)
+            .or_insert(1);
+    }
+    append_play_record(&record.finish(listened_secs, skipped)).await
+}
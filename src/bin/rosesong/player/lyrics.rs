@@ -0,0 +1,51 @@
+use log::error;
+use rosesong::{
+    bili_client::BiliClient,
+    lyrics::{load_lyrics, Lyrics},
+    model::{Track, TrackId},
+};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// 获取歌词失败后重试的间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+enum LyricsState {
+    /// 确认没有字幕的歌曲缓存为 None 避免重复请求
+    Loaded(Option<Lyrics>),
+    /// 网络错误等临时问题, 记录失败的时间, 间隔一段时间后重试
+    Failed(Instant),
+}
+
+static CURRENT_LYRICS: LazyLock<Mutex<Option<(TrackId, LyricsState)>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+/// 当前歌曲播放到 position 秒时的歌词, 没有歌词时返回空字符串
+pub async fn current_lyric_line(client: &BiliClient, track: &Track, position: f64) -> String {
+    let mut current = CURRENT_LYRICS.lock().await;
+    let id = track.id();
+    let need_load = match current.as_ref() {
+        Some((cached, _)) if *cached != id => true,
+        Some((_, LyricsState::Loaded(_))) => false,
+        Some((_, LyricsState::Failed(failed_at))) => failed_at.elapsed() >= RETRY_INTERVAL,
+        None => true,
+    };
+    if need_load {
+        let state = match load_lyrics(client, track).await {
+            Ok(lyrics) => LyricsState::Loaded(lyrics),
+            Err(e) => {
+                error!("Failed to load lyrics of {}: {}", id, e);
+                LyricsState::Failed(Instant::now())
+            }
+        };
+        *current = Some((id, state));
+    }
+    match current.as_ref() {
+        Some((_, LyricsState::Loaded(Some(lyrics)))) => lyrics
+            .line_at(position)
+            .map(|line| line.text.clone())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
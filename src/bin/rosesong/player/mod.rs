@@ -1,4 +1,6 @@
 pub mod gst_logic;
+pub mod history;
+pub mod lyrics;
 pub mod network;
 pub mod playlist;
 pub use gst_logic::Audio;
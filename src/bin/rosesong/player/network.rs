@@ -1,20 +1,22 @@
 use crate::bilibili::fetch_audio_url::fetch_audio_url;
+use crate::player::playlist::CONFIG;
 use glib::object::ObjectExt;
 use gstreamer::prelude::{ElementExt, ElementExtManual, GstBinExtManual, PadExt};
 use gstreamer::Pipeline;
 use log::{error, info};
-use reqwest::header::{ACCEPT, RANGE, USER_AGENT};
-use reqwest::Client;
+use reqwest::header::{ACCEPT, RANGE};
+use rosesong::bili_client::{BiliClient, BILI_REFERER, BILI_USER_AGENT};
 use rosesong::error::AppError;
+use rosesong::model::UnavailableReason;
 use tokio::time::{sleep, Duration};
 
-pub async fn verify_audio_url(client: &Client, url: &str) -> Result<bool, AppError> {
+pub async fn verify_audio_url(client: &BiliClient, url: &str) -> Result<bool, AppError> {
+    // User-Agent 和 Referer 已经是客户端的默认请求头
     let response = client
+        .http()
         .get(url)
-        .header(USER_AGENT, "Mozilla/5.0 BiliDroid/..* (bbcallen@gmail.com)")
         .header(ACCEPT, "*/*")
         .header(RANGE, "bytes=0-1024")
-        .header("Referer", "https://www.bilibili.com")
         .send()
         .await?;
 
@@ -22,15 +24,15 @@ pub async fn verify_audio_url(client: &Client, url: &str) -> Result<bool, AppErr
 }
 
 pub async fn fetch_and_verify_audio_url(
-    client: &Client,
+    client: &BiliClient,
     bvid: &str,
     cid: &str,
 ) -> Result<String, AppError> {
-    const MAX_RETRIES: u32 = 3;
     const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+    let max_retries = CONFIG.read().await.network.fetch_retries;
     let mut retry_delay = INITIAL_RETRY_DELAY;
 
-    for attempt in 1..=MAX_RETRIES {
+    for attempt in 1..=max_retries {
         match fetch_audio_url(client, bvid, cid).await {
             Ok(url) => match verify_audio_url(client, &url).await {
                 Ok(true) => return Ok(url),
@@ -41,12 +43,14 @@ pub async fn fetch_and_verify_audio_url(
                     error!("Error verifying URL: {}", e);
                 }
             },
+            // 视频已失效时重试没有意义
+            Err(e) if UnavailableReason::from_error(&e).is_some() => return Err(e),
             Err(e) => {
                 error!("Error fetching audio URL: {}", e);
             }
         }
-        if attempt < MAX_RETRIES {
-            info!("Retrying... Attempt {}/{}", attempt, MAX_RETRIES);
+        if attempt < max_retries {
+            info!("Retrying... Attempt {}/{}", attempt, max_retries);
             sleep(retry_delay).await;
             // Exponential backoff
             retry_delay *= 2;
@@ -69,20 +73,21 @@ pub async fn set_pipeline_uri_with_headers(
     source.set_property("location", url);
 
     let mut headers = gstreamer::Structure::new_empty("headers");
-    headers.set(
-        "User-Agent",
-        "Mozilla/5.0 BiliDroid/..* (bbcallen@gmail.com)",
-    );
-    headers.set("Referer", "https://www.bilibili.com");
+    headers.set("User-Agent", BILI_USER_AGENT);
+    headers.set("Referer", BILI_REFERER);
     source.set_property("extra-headers", &headers);
-    source.set_property("timeout", 5u32);
+    let config = CONFIG.read().await.clone();
+    source.set_property("timeout", config.network.stream_timeout_secs);
 
     // 创建 queue 作为缓存
     let queue = gstreamer::ElementFactory::make("queue")
         .build()
         .map_err(|_| AppError::Element("Failed to create queue element".to_string()))?;
-    queue.set_property("max-size-buffers", 100u32);
-    queue.set_property("max-size-time", 5 * gstreamer::ClockTime::SECOND);
+    queue.set_property("max-size-buffers", config.player.queue_max_buffers);
+    queue.set_property(
+        "max-size-time",
+        config.player.queue_max_secs * gstreamer::ClockTime::SECOND,
+    );
 
     let decodebin = gstreamer::ElementFactory::make("decodebin")
         .build()
@@ -1,50 +1,53 @@
-use rand::rng;
-use rand::seq::IteratorRandom;
+use log::error;
 use rosesong::{
+    config::Config,
     error::AppError,
-    model::{CurrentPlayInfo, PlayMode, Playlist, Track},
-    utils::{get_current_play_info, get_playlist, save_current_play_info},
+    history::{get_play_records, play_counts},
+    model::{CurrentPlayInfo, Playlist, PlaylistChange, Track, TrackSelection, UnavailableReason},
+    utils::{
+        get_current_play_info, get_playlist, get_smart_playlists, save_current_play_info,
+        update_playlist,
+    },
 };
 use std::sync::LazyLock;
 use tokio::sync::RwLock;
 
 // global variables
-pub static PLAYLIST: LazyLock<RwLock<Result<Playlist, AppError>>> = LazyLock::new(|| {
-    RwLock::new(Ok(Playlist {
-        tracks: Vec::new(),
-        seasons: Vec::new(),
-    }))
-});
+pub static PLAYLIST: LazyLock<RwLock<Result<Playlist, AppError>>> =
+    LazyLock::new(|| RwLock::new(Ok(Playlist::default())));
 pub static CURRENT_PLAY_INFO: LazyLock<RwLock<CurrentPlayInfo>> =
     LazyLock::new(|| RwLock::new(CurrentPlayInfo::default()));
+pub static CONFIG: LazyLock<RwLock<Config>> = LazyLock::new(|| RwLock::new(Config::default()));
 
 pub async fn set_current_track_index(index: usize) -> Result<(), AppError> {
     CURRENT_PLAY_INFO.write().await.set_current(index).await?;
     Ok(())
 }
 
+pub async fn jump_to_track_index(index: usize) -> Result<(), AppError> {
+    CURRENT_PLAY_INFO.write().await.jump_to(index).await?;
+    Ok(())
+}
+
 pub async fn load() -> Result<(), AppError> {
     // playlist
-    let playlist = get_playlist().await.unwrap_or_default();
+    let mut playlist = get_playlist().await?;
+    let play_counts = play_counts(&get_play_records().await.unwrap_or_default());
+    let smart_playlists = get_smart_playlists().await.unwrap_or_else(|e| {
+        error!("Failed to load smart playlists: {}", e);
+        Vec::new()
+    });
+    playlist.refresh_smart_playlists(smart_playlists, &play_counts);
     let mut playlist_lock = PLAYLIST.write().await;
     // Replace the old playlist with the new one
     *playlist_lock = Ok(playlist.clone());
 
     // current play info
-    let mut current_play_info = get_current_play_info().await.unwrap_or_default();
+    let mut current_play_info = get_current_play_info().await?;
     // 初始化播放列表
-    let tracks = if let Some(sid) = current_play_info.playing_sid.clone() {
-        let mut tracks_ = playlist.find_tracks_in_season(&sid);
-        if tracks_.is_empty() {
-            // 当前播放合集里的歌曲已经全部清空了, 改为播放全部歌曲
-            current_play_info.playing_sid = None;
-            tracks_ = playlist.tracks;
-        }
-        tracks_
-    } else {
-        playlist.tracks
-    };
-    current_play_info.current_tracks.clone_from(&tracks);
+    let tracks = current_play_info.select_tracks(&playlist);
+    current_play_info.replace_tracks(tracks.clone());
+    current_play_info.play_counts = play_counts;
 
     let index = current_play_info.index;
     if tracks.is_empty() {
@@ -56,7 +59,9 @@ pub async fn load() -> Result<(), AppError> {
         current_play_info.index = 0;
         current_play_info.track = tracks.first().cloned();
     } else {
-        current_play_info.move_to_next_track().await?;
+        let new_index = current_play_info.first_index();
+        current_play_info.index = new_index;
+        current_play_info.track = tracks.get(new_index).cloned();
     }
 
     save_current_play_info(&current_play_info).await?;
@@ -76,10 +81,9 @@ pub async fn get_current_track() -> Result<Track, AppError> {
         ))
 }
 
-pub async fn move_to_next_track() -> Result<(), AppError> {
+pub async fn move_to_next_track() -> Result<bool, AppError> {
     let mut current_play_info = CURRENT_PLAY_INFO.write().await;
-    current_play_info.move_to_next_track().await?;
-    Ok(())
+    current_play_info.move_to_next_track().await
 }
 
 pub async fn move_to_previous_track() -> Result<(), AppError> {
@@ -88,34 +92,38 @@ pub async fn move_to_previous_track() -> Result<(), AppError> {
     Ok(())
 }
 
+/// 将失效的视频记录到歌曲列表文件中, 之后加载歌曲列表时不再播放
+pub async fn mark_unavailable(bvid: &str, reason: UnavailableReason) -> Result<(), AppError> {
+    let file_bvid = bvid.to_string();
+    update_playlist(move |playlist| playlist.mark_unavailable(&file_bvid, reason)).await?;
+    if let Ok(playlist) = PLAYLIST.write().await.as_mut() {
+        playlist.mark_unavailable(bvid, reason);
+    }
+    Ok(())
+}
+
+/// 修改歌曲列表并保存到文件, 返回受影响的数量和歌曲是否发生变化,
+/// 歌曲变化时需要重新加载歌曲列表
+pub async fn apply_playlist_change(change: PlaylistChange) -> Result<(usize, bool), AppError> {
+    update_playlist(move |playlist| {
+        let old_tracks = playlist.tracks.clone();
+        let count = playlist.apply(change);
+        (count, playlist.tracks != old_tracks)
+    })
+    .await
+}
+
 pub async fn update_current_play_tracks(
-    sid: Option<String>,
+    selection: TrackSelection,
     tracks: Vec<Track>,
 ) -> Result<(), AppError> {
     let mut current_play_info = CURRENT_PLAY_INFO.write().await;
-    let play_mode = current_play_info.play_mode;
-    let new_index = match play_mode {
-        PlayMode::Shuffle => {
-            if tracks.is_empty() {
-                0
-            } else {
-                let mut rng = rng();
-                (0..tracks.len()).choose(&mut rng).ok_or_else(|| {
-                    AppError::DataParsing("Failed to choose random track".to_string())
-                })?
-            }
-        }
-        _ => 0,
-    };
+    current_play_info.set_selection(selection);
+    current_play_info.current_tracks.clone_from(&tracks);
+    current_play_info.reset_play_order();
+    let new_index = current_play_info.first_index();
     current_play_info.index = new_index;
-    current_play_info.playing_sid = sid;
-    if tracks.is_empty() {
-        current_play_info.current_tracks = Vec::new();
-        current_play_info.track = None;
-    } else {
-        current_play_info.current_tracks.clone_from(&tracks);
-        current_play_info.track = tracks.get(new_index).cloned();
-    }
+    current_play_info.track = tracks.get(new_index).cloned();
     save_current_play_info(&current_play_info).await?;
     Ok(())
 }
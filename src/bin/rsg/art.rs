@@ -0,0 +1,143 @@
+use std::{
+    io::{Cursor, IsTerminal, Write},
+    path::Path,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::{imageops::FilterType, ImageFormat, RgbImage};
+use rosesong::error::AppError;
+
+/// 终端中显示封面的宽度 (像素)
+const ART_WIDTH: u32 = 240;
+
+/// 终端支持的图片协议
+enum Protocol {
+    Kitty,
+    Sixel,
+}
+
+// 根据环境变量判断终端支持的图片协议, 无法判断时不显示图片
+fn detect_protocol() -> Option<Protocol> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    let term = std::env::var("TERM").unwrap_or_default();
+    let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+    if std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || term_program == "WezTerm"
+    {
+        Some(Protocol::Kitty)
+    } else if term.starts_with("foot") || term.contains("mlterm") || term.contains("sixel") {
+        Some(Protocol::Sixel)
+    } else {
+        None
+    }
+}
+
+/// 在终端中显示图片, 终端不支持图片协议时什么都不做
+pub fn print_image(path: &Path) -> Result<(), AppError> {
+    let Some(protocol) = detect_protocol() else {
+        return Ok(());
+    };
+    let image = image::open(path)
+        .map_err(|e| AppError::DataParsing(format!("读取封面失败: {e}")))?
+        .resize(ART_WIDTH, ART_WIDTH, FilterType::Triangle)
+        .to_rgb8();
+    let escape = match protocol {
+        Protocol::Kitty => kitty_escape(&image)?,
+        Protocol::Sixel => sixel_escape(&image),
+    };
+    let mut stdout = std::io::stdout();
+    stdout.write_all(escape.as_bytes())?;
+    writeln!(stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+// kitty 图片协议: PNG 数据经过 base64 编码后按 4096 字节分块传输
+fn kitty_escape(image: &RgbImage) -> Result<String, AppError> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| AppError::DataParsing(format!("编码封面失败: {e}")))?;
+    let encoded = STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+    let mut escape = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let control = if i == 0 {
+            format!("f=100,a=T,m={more}")
+        } else {
+            format!("m={more}")
+        };
+        escape.push_str(&format!(
+            "\x1b_G{control};{}\x1b\\",
+            String::from_utf8_lossy(chunk)
+        ));
+    }
+    Ok(escape)
+}
+
+// sixel 协议: 使用固定的 6x6x6 色板, 每 6 行像素为一个条带逐色输出
+fn sixel_escape(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+    let level = |v: u8| usize::from(v) * 5 / 255;
+    let color_index = |x: u32, y: u32| {
+        let [r, g, b] = image.get_pixel(x, y).0;
+        level(r) * 36 + level(g) * 6 + level(b)
+    };
+    let mut escape = format!("\x1bPq\"1;1;{width};{height}");
+    for i in 0..216 {
+        let percent = |v: usize| v * 100 / 5;
+        escape.push_str(&format!(
+            "#{i};2;{};{};{}",
+            percent(i / 36),
+            percent(i / 6 % 6),
+            percent(i % 6)
+        ));
+    }
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        let mut colors: Vec<usize> = (0..width)
+            .flat_map(|x| (0..rows).map(move |dy| (x, band + dy)))
+            .map(|(x, y)| color_index(x, y))
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+        for color in colors {
+            escape.push_str(&format!("#{color}"));
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let bits = (0..rows)
+                    .filter(|&dy| color_index(x, band + dy) == color)
+                    .fold(0u8, |bits, dy| bits | 1 << dy);
+                let c = char::from(63 + bits);
+                run = match run {
+                    Some((prev, n)) if prev == c => Some((prev, n + 1)),
+                    Some((prev, n)) => {
+                        push_run(&mut escape, prev, n);
+                        Some((c, 1))
+                    }
+                    None => Some((c, 1)),
+                };
+            }
+            if let Some((prev, n)) = run {
+                push_run(&mut escape, prev, n);
+            }
+            escape.push('$');
+        }
+        escape.push('-');
+    }
+    escape.push_str("\x1b\\");
+    escape
+}
+
+fn push_run(escape: &mut String, c: char, n: usize) {
+    if n > 3 {
+        escape.push_str(&format!("!{n}{c}"));
+    } else {
+        escape.extend(std::iter::repeat_n(c, n));
+    }
+}
@@ -1,13 +1,15 @@
-use std::{io::Write, sync::mpsc};
+use std::{collections::HashMap, io::Write};
 
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use reqwest::Client;
+use indicatif::{ProgressBar, ProgressStyle};
 use rosesong::{
+    bili_client::BiliClient,
     error::AppError,
-    model::{Season, Track},
+    import_target::ImportTarget,
+    model::{CollectionKind, Season, Source, SourceKind, Track, UnavailableReason},
+    utils::parse_date,
 };
 use serde::Deserialize;
-use tokio::io::AsyncBufReadExt;
+use tokio::{io::AsyncBufReadExt, task::JoinSet};
 
 #[derive(Deserialize)]
 pub struct Owner {
@@ -18,10 +20,40 @@ pub struct Owner {
 pub struct VideoData {
     pub bvid: String,
     pub title: String,
+    #[serde(default)]
+    pub pic: String,
     pub cid: i64,
     pub owner: Owner,
+    #[serde(default)]
+    pub duration: u64,
+    #[serde(default)]
+    pub pubdate: i64,
+    #[serde(default)]
+    pub tid: u32,
+    #[serde(default)]
+    pub tname: String,
+    #[serde(default)]
+    pub desc: String,
+    pub stat: Option<Stat>,
     pub season_id: Option<i64>,
     pub ugc_season: Option<UgcSeason>,
+    #[serde(default)]
+    pub pages: Vec<Page>,
+}
+
+#[derive(Deserialize)]
+pub struct Stat {
+    pub view: u64,
+}
+
+// 多 P 视频的每个分 P
+#[derive(Deserialize)]
+pub struct Page {
+    pub cid: i64,
+    pub page: u32,
+    pub part: String,
+    #[serde(default)]
+    pub duration: u64,
 }
 
 #[derive(Deserialize)]
@@ -44,6 +76,28 @@ pub struct Episode {
     pub cid: i64,
     pub bvid: String,
     pub title: String,
+    pub arc: Option<EpisodeArc>,
+    /// 多 P 视频的所有分 P, `cid` 为第一个分 P
+    #[serde(default)]
+    pub pages: Vec<Page>,
+}
+
+#[derive(Deserialize)]
+pub struct EpisodeArc {
+    #[serde(default)]
+    pub pic: String,
+    #[serde(default)]
+    pub duration: u64,
+    #[serde(default)]
+    pub pubdate: i64,
+    #[serde(default)]
+    pub desc: String,
+    pub stat: Option<Stat>,
+}
+
+// 接口中缺少的数值为 0, 字符串为空
+fn non_zero<T: Default + PartialEq>(value: T) -> Option<T> {
+    (value != T::default()).then_some(value)
 }
 
 impl VideoData {
@@ -54,6 +108,7 @@ impl VideoData {
             cover: ugc_season.cover.clone(),
             intro: ugc_season.intro.clone(),
             owner: self.owner.name.clone(),
+            kind: CollectionKind::Season,
         })
     }
 
@@ -62,19 +117,64 @@ impl VideoData {
         if let Some(ugc_season) = &self.ugc_season {
             for section in &ugc_season.sections {
                 for episode in &section.episodes {
-                    tracks.push(Track {
+                    let mut track = Track {
                         bvid: episode.bvid.clone(),
                         cid: episode.cid.to_string(),
                         sid: Some(section.season_id.to_string()),
                         title: episode.title.clone(),
                         owner: self.owner.name.clone(),
-                    });
+                        ..Track::default()
+                    };
+                    if let Some(arc) = &episode.arc {
+                        track.cover.clone_from(&arc.pic);
+                        track.duration = non_zero(arc.duration);
+                        track.pubdate = non_zero(arc.pubdate);
+                        track.views = arc.stat.as_ref().map(|s| s.view);
+                        track.desc = non_zero(arc.desc.clone());
+                    }
+                    // 与按 bvid 导入相同, 多 P 视频每个分 P 生成一个 Track
+                    if episode.pages.len() <= 1 {
+                        tracks.push(track);
+                        continue;
+                    }
+                    tracks.extend(episode.pages.iter().map(|page| Track {
+                        cid: page.cid.to_string(),
+                        title: if page.part.is_empty() {
+                            format!("{} P{}", episode.title, page.page)
+                        } else {
+                            page.part.clone()
+                        },
+                        page: Some(page.page),
+                        duration: non_zero(page.duration),
+                        ..track.clone()
+                    }));
                 }
             }
         }
         tracks
     }
 
+    // 多 P 视频每个分 P 生成一个 Track, 标题使用分 P 标题
+    pub fn to_tracks(&self) -> Vec<Track> {
+        if self.pages.len() <= 1 {
+            return vec![self.to_track()];
+        }
+        self.pages
+            .iter()
+            .map(|page| Track {
+                cid: page.cid.to_string(),
+                title: if page.part.is_empty() {
+                    format!("{} P{}", self.title, page.page)
+                } else {
+                    page.part.clone()
+                },
+                page: Some(page.page),
+                duration: non_zero(page.duration),
+                ..self.to_track()
+            })
+            .collect()
+    }
+
     pub fn to_track(&self) -> Track {
         Track {
             bvid: self.bvid.clone(),
@@ -82,15 +182,18 @@ impl VideoData {
             sid: None,
             title: self.title.clone(),
             owner: self.owner.name.clone(),
+            cover: self.pic.clone(),
+            duration: non_zero(self.duration),
+            pubdate: non_zero(self.pubdate),
+            tid: non_zero(self.tid),
+            tname: non_zero(self.tname.clone()),
+            views: self.stat.as_ref().map(|s| s.view),
+            desc: non_zero(self.desc.clone()),
+            ..Track::default()
         }
     }
 }
 
-#[derive(Deserialize)]
-struct ApiResponse<T> {
-    data: T,
-}
-
 pub fn create_progress_bar(total: u64) -> ProgressBar {
     let pb = ProgressBar::new(total);
     pb.set_style(
@@ -105,178 +208,638 @@ pub fn create_progress_bar(total: u64) -> ProgressBar {
 }
 
 // 可通过该方法获取合集里的所有视频信息 (ugc_season -> sections -> episodes(合集里的所有视频数组对象))
-pub async fn fetch_video_data(client: &Client, bvid: &str) -> Result<VideoData, AppError> {
-    let url = format!("https://api.bilibili.com/x/web-interface/view?bvid={bvid}");
-    let response = client.get(&url).send().await.map_err(|e| {
-        eprintln!("Failed to send request to {url}: {e}");
-        AppError::HttpRequest(e)
-    })?;
-    let mut api_response: ApiResponse<VideoData> = response.json().await.map_err(|e| {
-        eprintln!("Failed to parse response from {url}: {e}");
-        AppError::HttpRequest(e)
-    })?;
-    api_response.data.bvid = bvid.to_string();
-    Ok(api_response.data)
-}
-
-pub async fn fetch_bvids_from_fid(client: &Client, fid: &str) -> Result<Vec<String>, AppError> {
-    let url = format!("https://api.bilibili.com/x/v3/fav/resource/ids?media_id={fid}");
-    let response = client.get(&url).send().await.map_err(|e| {
-        eprintln!("Failed to send request to {url}: {e}");
-        AppError::HttpRequest(e)
-    })?;
-    let json: serde_json::Value = response.json().await.map_err(|e| {
-        eprintln!("Failed to parse response from {url}: {e}");
-        AppError::HttpRequest(e)
-    })?;
-    let bvids: Vec<String> = json["data"]
-        .as_array()
-        .ok_or({
-            eprintln!("Failed to find 'data' array in response from {url}");
-            AppError::DataParsing("数据中缺少 bvids 数组".to_string())
-        })?
+pub async fn fetch_video_data(client: &BiliClient, bvid: &str) -> Result<VideoData, AppError> {
+    let url = "https://api.bilibili.com/x/web-interface/view";
+    let mut video_data: VideoData =
+        client
+            .get(url, &[("bvid", bvid)])
+            .await
+            .map_err(|e| match e {
+                AppError::NotFound(_) => AppError::VideoDeleted(bvid.to_string()),
+                e => e,
+            })?;
+    video_data.bvid = bvid.to_string();
+    Ok(video_data)
+}
+
+/// 来源中的一个视频, 列表接口的信息足够时直接带有对应的歌曲, 否则需要再请求 view 接口
+pub struct RemoteEntry {
+    pub bvid: String,
+    pub title: String,
+    pub track: Option<Track>,
+}
+
+/// 从导入来源获取到的视频列表
+pub struct FetchedSource {
+    pub title: String,
+    pub entries: Vec<RemoteEntry>,
+    pub season: Option<Season>,
+}
+
+/// 导入得到的歌曲, 以及需要记录的合集和导入来源
+pub struct FetchedTracks {
+    pub tracks: Vec<Track>,
+    pub season: Option<Season>,
+    pub source: Option<Source>,
+    pub failures: Vec<FetchFailure>,
+}
+
+/// 获取信息失败的视频, 导入结束后统一报告
+pub struct FetchFailure {
+    pub bvid: String,
+    pub error: AppError,
+}
+
+#[derive(Deserialize)]
+struct FavUpper {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct FavUgc {
+    first_cid: i64,
+}
+
+#[derive(Deserialize)]
+struct FavCount {
+    play: u64,
+}
+
+#[derive(Deserialize)]
+struct FavMedia {
+    // 2 为视频, 12 为音频, 21 为合集
+    #[serde(rename = "type")]
+    media_type: i32,
+    title: String,
+    bvid: String,
+    // 分 P 数
+    #[serde(default)]
+    page: u32,
+    // 0 为正常, 其他值为已失效 (如 9 为 UP 主删除, 1 为其他原因失效)
+    attr: i32,
+    #[serde(default)]
+    cover: String,
+    #[serde(default)]
+    intro: String,
+    #[serde(default)]
+    duration: u64,
+    #[serde(default)]
+    pubtime: i64,
+    cnt_info: Option<FavCount>,
+    upper: FavUpper,
+    ugc: Option<FavUgc>,
+}
+
+#[derive(Deserialize)]
+struct FavInfo {
+    title: String,
+    media_count: u64,
+}
+
+#[derive(Deserialize)]
+struct FavList {
+    info: FavInfo,
+    medias: Option<Vec<FavMedia>>,
+    has_more: bool,
+}
+
+// 分页获取收藏夹中的视频, 列表接口已包含标题、作者和 cid,
+// 只有多 P 视频需要额外请求 view 接口获取每个分 P 的信息
+pub async fn fetch_favorite(client: &BiliClient, fid: &str) -> Result<FetchedSource, AppError> {
+    let url = "https://api.bilibili.com/x/v3/fav/resource/list";
+    let mut title;
+    let mut entries = Vec::new();
+    let mut invalid = Vec::new();
+    let mut pb = None;
+    let mut pn = 1;
+    loop {
+        let params = [
+            ("media_id", fid),
+            ("platform", "web"),
+            ("ps", "20"),
+            ("pn", &pn.to_string()),
+        ];
+        let list: FavList = client.get(url, &params).await.map_err(|e| match e {
+            AppError::NotFound(_) | AppError::AccessDenied(_) | AppError::DataParsing(_) => {
+                AppError::InvalidInput("提供的 fid 无效或收藏夹不可访问".to_string())
+            }
+            e => e,
+        })?;
+        title = list.info.title;
+        let pb = pb.get_or_insert_with(|| create_progress_bar(list.info.media_count));
+        let medias = list.medias.unwrap_or_default();
+        pb.inc(medias.len() as u64);
+        for media in medias {
+            if media.media_type != 2 {
+                invalid.push(format!("{} (不支持的类型)", media.title));
+                continue;
+            }
+            if media.attr != 0 {
+                invalid.push(format!("{} ({})", media.title, media.bvid));
+                continue;
+            }
+            let track = match media.ugc {
+                Some(ugc) if media.page <= 1 => Some(Track {
+                    bvid: media.bvid.clone(),
+                    cid: ugc.first_cid.to_string(),
+                    sid: None,
+                    title: media.title.clone(),
+                    owner: media.upper.name,
+                    cover: media.cover,
+                    duration: non_zero(media.duration),
+                    pubdate: non_zero(media.pubtime),
+                    views: media.cnt_info.map(|c| c.play),
+                    desc: non_zero(media.intro),
+                    ..Track::default()
+                }),
+                _ => None,
+            };
+            entries.push(RemoteEntry {
+                bvid: media.bvid,
+                title: media.title,
+                track,
+            });
+        }
+        if !list.has_more {
+            break;
+        }
+        pn += 1;
+    }
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+
+    if !invalid.is_empty() {
+        println!("跳过 {} 个失效或不支持的收藏:", invalid.len());
+        for title in &invalid {
+            println!("  {title}");
+        }
+    }
+    Ok(FetchedSource {
+        title,
+        entries,
+        season: None,
+    })
+}
+
+// 并发获取会打乱顺序, 按 bvids 中的顺序重新排列, 同一视频的分 P 保持原有顺序
+fn sort_tracks_by_bvids(tracks: &mut [Track], bvids: &[String]) {
+    let order: HashMap<&str, usize> = bvids
         .iter()
-        .filter_map(|v| v["bvid"].as_str().map(String::from))
+        .enumerate()
+        .map(|(i, b)| (b.as_str(), i))
         .collect();
+    tracks.sort_by_key(|t| order.get(t.bvid.as_str()).copied());
+}
 
-    if bvids.is_empty() {
-        return Err(AppError::InvalidInput(
-            "提供的 fid 无效或没有找到相关的视频".to_string(),
-        ));
+/// 将来源中的视频转换为歌曲, 缺少信息的视频通过 view 接口获取, series_id 为歌曲所属的系列
+pub async fn resolve_entries(
+    client: &BiliClient,
+    entries: Vec<RemoteEntry>,
+    series_id: Option<&str>,
+    failures: &mut Vec<FetchFailure>,
+) -> Vec<Track> {
+    let order: Vec<String> = entries.iter().map(|e| e.bvid.clone()).collect();
+    let mut tracks = Vec::new();
+    let mut missing = Vec::new();
+    for entry in entries {
+        match entry.track {
+            Some(track) => tracks.push(track),
+            None => missing.push(entry.bvid),
+        }
+    }
+    if !missing.is_empty() {
+        let mut fetched = Vec::new();
+        batch_fetch_audio_info(client, &mut fetched, &missing, failures).await;
+        if let Some(series_id) = series_id {
+            for track in &mut fetched {
+                track.series_id = Some(series_id.to_string());
+            }
+        }
+        tracks.extend(fetched);
+        sort_tracks_by_bvids(&mut tracks, &order);
     }
+    tracks
+}
 
-    Ok(bvids)
+#[derive(Deserialize)]
+struct UploadArchive {
+    bvid: String,
+    title: String,
+    pubdate: i64,
+}
+
+#[derive(Deserialize)]
+struct UploadPage {
+    num: u32,
+    size: u32,
+    total: u32,
+}
+
+#[derive(Deserialize)]
+struct UploadList {
+    #[serde(default)]
+    archives: Vec<UploadArchive>,
+    page: UploadPage,
+}
+
+// 分页获取投稿列表, 用于 UP 主的投稿和系列. 列表按发布时间从新到旧排列时可以指定 since,
+// 遇到早于 since 发布的投稿后不再获取后面的页
+async fn fetch_archives(
+    client: &BiliClient,
+    url: &str,
+    params: &[(&str, &str)],
+    page_size: u32,
+    since: Option<i64>,
+) -> Result<Vec<UploadArchive>, AppError> {
+    let page_size = page_size.to_string();
+    let mut archives = Vec::new();
+    let mut pn = 1;
+    loop {
+        let pn_str = pn.to_string();
+        let mut page_params = params.to_vec();
+        page_params.extend([("ps", page_size.as_str()), ("pn", pn_str.as_str())]);
+        let list: UploadList = client.get(url, &page_params).await?;
+        let reached_since =
+            since.is_some_and(|since| list.archives.iter().any(|a| a.pubdate < since));
+        let is_last_page = reached_since
+            || list.archives.is_empty()
+            || list.page.num * list.page.size >= list.page.total;
+        archives.extend(list.archives);
+        if is_last_page {
+            break;
+        }
+        pn += 1;
+    }
+    Ok(archives)
+}
+
+// 获取 UP 主的投稿, keyword 为空时返回全部投稿, since 为发布时间下限 (时间戳)
+pub async fn fetch_uploads(
+    client: &BiliClient,
+    mid: &str,
+    keyword: Option<&str>,
+    since: Option<i64>,
+) -> Result<Vec<RemoteEntry>, AppError> {
+    let url = "https://api.bilibili.com/x/series/recArchivesByKeywords";
+    // 该接口返回的投稿按发布时间从新到旧排列
+    let params = [("mid", mid), ("keywords", keyword.unwrap_or_default())];
+    let archives = fetch_archives(client, url, &params, 50, since).await?;
+    Ok(archives
+        .into_iter()
+        .filter(|a| since.is_none_or(|since| a.pubdate >= since))
+        .map(|a| RemoteEntry {
+            bvid: a.bvid,
+            title: a.title,
+            track: None,
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct SeriesInfo {
+    meta: SeriesMeta,
+}
+
+#[derive(Deserialize)]
+struct SeriesMeta {
+    mid: i64,
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    cover: String,
+}
+
+// 获取系列 (列表) 的信息和其中的所有视频, 系列是合集之前的旧版视频列表
+pub async fn fetch_series(client: &BiliClient, series_id: &str) -> Result<FetchedSource, AppError> {
+    let url = "https://api.bilibili.com/x/series/series";
+    let info: SeriesInfo = client.get(url, &[("series_id", series_id)]).await?;
+    let meta = info.meta;
+
+    let url = "https://api.bilibili.com/x/series/archives";
+    let mid = meta.mid.to_string();
+    let params = [
+        ("mid", mid.as_str()),
+        ("series_id", series_id),
+        ("sort", "asc"),
+    ];
+    let archives = fetch_archives(client, url, &params, 100, None).await?;
+
+    let series = Season {
+        id: series_id.to_string(),
+        title: meta.name.clone(),
+        cover: meta.cover,
+        intro: meta.description,
+        owner: String::new(),
+        kind: CollectionKind::Series,
+    };
+    Ok(FetchedSource {
+        title: meta.name,
+        entries: archives
+            .into_iter()
+            .map(|a| RemoteEntry {
+                bvid: a.bvid,
+                title: a.title,
+                track: None,
+            })
+            .collect(),
+        season: Some(series),
+    })
+}
+
+#[derive(Deserialize)]
+struct SeasonMedia {
+    bvid: String,
+}
+
+#[derive(Deserialize)]
+struct SeasonMedias {
+    medias: Option<Vec<SeasonMedia>>,
 }
 
 pub async fn fetch_bvids_from_session_id(
-    client: &Client,
+    client: &BiliClient,
     season_id: &str,
 ) -> Result<Vec<String>, AppError> {
-    let url = format!("https://api.bilibili.com/x/space/fav/season/list?season_id={season_id}");
-    let response = client.get(&url).send().await.map_err(|e| {
-        eprintln!("Failed to send request to {url}: {e}");
-        AppError::HttpRequest(e)
-    })?;
-    let json: serde_json::Value = response.json().await.map_err(|e| {
-        eprintln!("Failed to parse response from {url}: {e}");
-        AppError::HttpRequest(e)
-    })?;
-    let bvids: Vec<String> = json["data"]["medias"]
-        .as_array()
-        .ok_or_else(|| {
-            eprintln!("Failed to find 'data' array in response from {url}");
-            AppError::DataParsing("数据中缺少 bvids 数组".to_string())
-        })?
-        .iter()
-        .filter_map(|v| v["bvid"].as_str().map(String::from))
+    let url = "https://api.bilibili.com/x/space/fav/season/list";
+    let season: SeasonMedias = client.get(url, &[("season_id", season_id)]).await?;
+    let bvids: Vec<String> = season
+        .medias
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| m.bvid)
         .collect();
 
     if bvids.is_empty() {
         return Err(AppError::InvalidInput(
-            "提供的 fid 无效或没有找到相关的视频".to_string(),
+            "提供的 sid 无效或没有找到相关的视频".to_string(),
         ));
     }
 
     Ok(bvids)
 }
 
+// 通过合集中第一个视频的 view 信息获取整个合集
+pub async fn fetch_season(client: &BiliClient, season_id: &str) -> Result<FetchedSource, AppError> {
+    let bvids = fetch_bvids_from_session_id(client, season_id).await?;
+    let video_data = fetch_video_data(client, &bvids[0]).await?;
+    let season = video_data.to_season();
+    Ok(FetchedSource {
+        title: season.as_ref().map(|s| s.title.clone()).unwrap_or_default(),
+        entries: video_data
+            .to_tracks_by_season()
+            .into_iter()
+            .map(|t| RemoteEntry {
+                bvid: t.bvid.clone(),
+                title: t.title.clone(),
+                track: Some(t),
+            })
+            .collect(),
+        season,
+    })
+}
+
+/// 重新获取导入来源中的视频, 用于同步
+pub async fn fetch_source(client: &BiliClient, source: &Source) -> Result<FetchedSource, AppError> {
+    match source.kind {
+        SourceKind::Favorite => fetch_favorite(client, &source.id).await,
+        SourceKind::Season => fetch_season(client, &source.id).await,
+        SourceKind::Series => fetch_series(client, &source.id).await,
+        SourceKind::Owner => {
+            let since = source.since.as_deref().map(parse_since).transpose()?;
+            let entries =
+                fetch_uploads(client, &source.id, source.keyword.as_deref(), since).await?;
+            Ok(FetchedSource {
+                title: source.title.clone(),
+                entries,
+                season: None,
+            })
+        }
+    }
+}
+
+fn parse_since(date: &str) -> Result<i64, AppError> {
+    parse_date(date).ok_or_else(|| AppError::InvalidInput(format!("无效的日期 {date}")))
+}
+
+pub async fn confirm(prompt: &str) -> bool {
+    print!("{prompt}");
+    std::io::stdout().flush().unwrap();
+    let mut confirmation = String::new();
+    let mut stdin = tokio::io::BufReader::new(tokio::io::stdin());
+    stdin
+        .read_line(&mut confirmation)
+        .await
+        .expect("Failed to read line");
+    confirmation.trim().eq_ignore_ascii_case("y")
+}
+
+fn new_source(kind: SourceKind, id: String, fetched: &FetchedSource) -> Source {
+    let mut source = Source::new(kind, id, fetched.title.clone());
+    source.set_bvids(fetched.entries.iter().map(|e| e.bvid.as_str()));
+    source.synced_at = Some(chrono::Local::now().timestamp());
+    source
+}
+
 pub async fn get_tracks(
-    client: &Client,
-    fid: Option<String>,
-    bvid: Option<String>,
-    sid: Option<String>,
-) -> Result<(Vec<Track>, Option<Season>), AppError> {
+    client: &BiliClient,
+    target: ImportTarget,
+) -> Result<FetchedTracks, AppError> {
     let mut track_list = Vec::new();
     let mut season = None;
+    let mut source = None;
+    let mut failures = Vec::new();
 
-    if let Some(fid) = fid {
-        let bvids = fetch_bvids_from_fid(client, &fid).await?;
-        batch_fetch_audio_info(client, &mut track_list, &bvids)?;
-    } else if let Some(bvid) = bvid {
-        let video_data = fetch_video_data(client, &bvid).await?;
-        if video_data.season_id.is_some() {
-            print!(
-                "该歌曲位于合集 [{}] 中，是否导入该合集? [y/n]: ",
-                video_data
-                    .ugc_season
-                    .as_ref()
-                    .map(|i| i.title.clone())
-                    .unwrap_or_default()
-            );
-            std::io::stdout().flush().unwrap();
-            let mut confirmation = String::new();
-            let mut stdin = tokio::io::BufReader::new(tokio::io::stdin());
-            stdin
-                .read_line(&mut confirmation)
-                .await
-                .expect("Failed to read line");
-            if confirmation.trim().eq_ignore_ascii_case("y") {
+    match target {
+        ImportTarget::Favorite { fid } => {
+            let fetched = fetch_favorite(client, &fid).await?;
+            source = Some(new_source(SourceKind::Favorite, fid, &fetched));
+            track_list = resolve_entries(client, fetched.entries, None, &mut failures).await;
+        }
+        ImportTarget::Video {
+            bvid,
+            page: Some(page),
+        } => {
+            // 链接中指定了分 P 时只导入该分 P
+            let video_data = fetch_video_data(client, &bvid).await?;
+            let track = video_data
+                .to_tracks()
+                .into_iter()
+                .find(|t| t.page.unwrap_or(1) == page)
+                .ok_or_else(|| {
+                    AppError::InvalidInput(format!("视频 {bvid} 没有第 {page} 个分P"))
+                })?;
+            track_list.push(track);
+        }
+        ImportTarget::Video { bvid, page: None } => {
+            let video_data = fetch_video_data(client, &bvid).await?;
+            let mut import_season = false;
+            if video_data.season_id.is_some() {
+                let prompt = format!(
+                    "该歌曲位于合集 [{}] 中，是否导入该合集? [y/n]: ",
+                    video_data
+                        .ugc_season
+                        .as_ref()
+                        .map(|i| i.title.clone())
+                        .unwrap_or_default()
+                );
+                season = video_data.to_season();
+                import_season = confirm(&prompt).await;
+            }
+            // 不导入合集时与普通视频一样选择分P
+            if import_season {
                 track_list.extend(video_data.to_tracks_by_season());
+                if let Some(season) = &season {
+                    let mut new =
+                        Source::new(SourceKind::Season, season.id.clone(), season.title.clone());
+                    new.set_bvids(track_list.iter().map(|t| t.bvid.as_str()));
+                    new.synced_at = Some(chrono::Local::now().timestamp());
+                    source = Some(new);
+                }
+            } else if video_data.pages.len() > 1 {
+                let prompt = format!(
+                    "该视频共有 {} 个分P，是否全部导入? [y/n]: ",
+                    video_data.pages.len()
+                );
+                if confirm(&prompt).await {
+                    track_list.extend(video_data.to_tracks());
+                } else {
+                    track_list.push(video_data.to_track());
+                }
             } else {
                 track_list.push(video_data.to_track());
             }
-            season = video_data.to_season();
-        } else {
-            track_list.push(video_data.to_track());
         }
-    } else if let Some(season_id) = sid {
-        let bvids = fetch_bvids_from_session_id(client, &season_id).await?;
-        let video_data = fetch_video_data(client, &bvids[0]).await?;
-        track_list.extend(video_data.to_tracks_by_season());
-        season = video_data.to_season();
-    } else {
-        return Err(AppError::InvalidInput(
-            "请提供正确的 fid 或 bvid 或 sid".to_string(),
-        ));
+        ImportTarget::Season { sid } => {
+            let fetched = fetch_season(client, &sid).await?;
+            source = Some(new_source(SourceKind::Season, sid, &fetched));
+            season = fetched.season;
+            track_list = resolve_entries(client, fetched.entries, None, &mut failures).await;
+        }
+        ImportTarget::Series { sid } => {
+            let fetched = fetch_series(client, &sid).await?;
+            source = Some(new_source(SourceKind::Series, sid.clone(), &fetched));
+            season = fetched.season;
+            track_list = resolve_entries(client, fetched.entries, Some(&sid), &mut failures).await;
+            if let Some(series) = &mut season {
+                series.owner = track_list
+                    .first()
+                    .map(|t| t.owner.clone())
+                    .unwrap_or_default();
+            }
+        }
+        ImportTarget::Space {
+            mid,
+            keyword,
+            since,
+        } => {
+            let since_ts = since.as_deref().map(parse_since).transpose()?;
+            let entries = fetch_uploads(client, &mid, keyword.as_deref(), since_ts).await?;
+            if entries.is_empty() {
+                return Err(AppError::InvalidInput("没有找到符合条件的投稿".to_string()));
+            }
+            let fetched = FetchedSource {
+                title: String::new(),
+                entries,
+                season: None,
+            };
+            let mut new = new_source(SourceKind::Owner, mid, &fetched);
+            new.keyword = keyword;
+            new.since = since;
+            track_list = resolve_entries(client, fetched.entries, None, &mut failures).await;
+            new.title = track_list
+                .first()
+                .map(|t| t.owner.clone())
+                .unwrap_or_default();
+            source = Some(new);
+        }
     }
 
     if track_list.is_empty() {
+        print_failures(&failures);
         return Err(AppError::InvalidInput(
             "提供的 bvid 或 fid 或 sid 无效或没有找到相关的视频".to_string(),
         ));
     }
 
-    Ok((track_list, season))
+    Ok(FetchedTracks {
+        tracks: track_list,
+        season,
+        source,
+        failures,
+    })
 }
 
-fn batch_fetch_audio_info(
-    client: &Client,
+// 并发获取视频信息, 并发数和请求频率由客户端限制, 失败的视频记录到 failures 中
+async fn batch_fetch_audio_info(
+    client: &BiliClient,
     track_list: &mut Vec<Track>,
     bvids: &[String],
-) -> Result<(), AppError> {
-    let (send, recv) = mpsc::channel();
-    let m = MultiProgress::new();
-    let batch_bvids = bvids
-        .chunks(100)
-        .map(<[String]>::to_vec)
-        .collect::<Vec<Vec<String>>>();
-
-    for batch in batch_bvids {
-        let pb = m.add(create_progress_bar(batch.clone().len() as u64));
-        let task_data = batch.clone();
-        let send_ = send.clone();
-        let client_ = client.clone();
-        tokio::spawn(async move {
-            for task_bvid in task_data {
-                let video_data = fetch_video_data(&client_, &task_bvid).await.unwrap();
-                send_.send(video_data).unwrap();
-                pb.inc(1);
-            }
-            pb.finish_and_clear();
+    failures: &mut Vec<FetchFailure>,
+) {
+    let pb = create_progress_bar(bvids.len() as u64);
+    let mut tasks = JoinSet::new();
+    for bvid in bvids {
+        let client = client.clone();
+        let bvid = bvid.clone();
+        tasks.spawn(async move {
+            let result = fetch_video_data(&client, &bvid).await;
+            (bvid, result)
         });
     }
 
-    drop(send);
+    while let Some(joined) = tasks.join_next().await {
+        pb.inc(1);
+        match joined {
+            Ok((_, Ok(video_data))) => track_list.extend(video_data.to_tracks()),
+            Ok((bvid, Err(error))) => failures.push(FetchFailure { bvid, error }),
+            Err(e) => log::error!("Failed to join fetch task: {e}"),
+        }
+    }
+    pb.finish_and_clear();
+}
 
-    for video_data in recv {
-        track_list.extend(video_data.to_tracks_by_season());
+/// 检查视频是否仍然可以播放, 返回每个视频失效的原因, 可以播放时为 None.
+/// 因网络等原因无法判断的视频不在结果中
+pub async fn check_availability(
+    client: &BiliClient,
+    bvids: &[String],
+) -> Vec<(String, Option<UnavailableReason>)> {
+    let pb = create_progress_bar(bvids.len() as u64);
+    let mut tasks = JoinSet::new();
+    for bvid in bvids {
+        let client = client.clone();
+        let bvid = bvid.clone();
+        tasks.spawn(async move {
+            let result = fetch_video_data(&client, &bvid).await;
+            (bvid, result)
+        });
     }
 
-    if track_list.is_empty() {
-        return Err(AppError::InvalidInput(
-            "提供的 bvid 无效或没有找到相关的视频".to_string(),
-        ));
+    let mut results = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        pb.inc(1);
+        match joined {
+            Ok((bvid, Ok(_))) => results.push((bvid, None)),
+            Ok((bvid, Err(e))) => match UnavailableReason::from_error(&e) {
+                Some(reason) => results.push((bvid, Some(reason))),
+                None => pb.println(format!("无法检查 {bvid}: {e}")),
+            },
+            Err(e) => log::error!("Failed to join fetch task: {e}"),
+        }
     }
+    pb.finish_and_clear();
+    results
+}
 
-    Ok(())
+/// 打印获取失败的视频
+pub fn print_failures(failures: &[FetchFailure]) {
+    if failures.is_empty() {
+        return;
+    }
+    println!("{} 个视频获取信息失败, 已跳过:", failures.len());
+    for failure in failures {
+        println!("  {}: {}", failure.bvid, failure.error);
+    }
 }
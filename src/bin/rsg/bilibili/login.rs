@@ -0,0 +1,56 @@
+use rosesong::{bili_client::BiliClient, error::AppError};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct QrCodeData {
+    pub url: String,
+    pub qrcode_key: String,
+}
+
+#[derive(Deserialize)]
+struct QrPollData {
+    #[serde(default)]
+    url: String,
+    code: i64,
+}
+
+/// 扫码登录的状态
+pub enum QrStatus {
+    /// 未扫码
+    Waiting,
+    /// 已扫码, 等待在手机上确认
+    Scanned,
+    /// 二维码已失效
+    Expired,
+    /// 登录成功, 附带包含 cookie 的跳转链接
+    Confirmed(String),
+}
+
+// 申请登录二维码, url 为二维码的内容
+pub async fn generate_qrcode(client: &BiliClient) -> Result<QrCodeData, AppError> {
+    let url = "https://passport.bilibili.com/x/passport-login/web/qrcode/generate";
+    client.get(url, &[]).await
+}
+
+pub async fn poll_qrcode(client: &BiliClient, qrcode_key: &str) -> Result<QrStatus, AppError> {
+    let url = "https://passport.bilibili.com/x/passport-login/web/qrcode/poll";
+    let data: QrPollData = client.get(url, &[("qrcode_key", qrcode_key)]).await?;
+    match data.code {
+        0 => Ok(QrStatus::Confirmed(data.url)),
+        86090 => Ok(QrStatus::Scanned),
+        86038 => Ok(QrStatus::Expired),
+        _ => Ok(QrStatus::Waiting),
+    }
+}
+
+// 退出登录, 使服务器上的 cookie 失效
+pub async fn logout(client: &BiliClient, bili_jct: &str) -> Result<(), AppError> {
+    let url = "https://passport.bilibili.com/login/exit/v2";
+    client
+        .http()
+        .post(url)
+        .form(&[("biliCSRF", bili_jct)])
+        .send()
+        .await?;
+    Ok(())
+}
@@ -1 +1,3 @@
 pub mod fetch_audio_info;
+pub mod login;
+pub mod search;
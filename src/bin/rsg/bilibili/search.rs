@@ -0,0 +1,196 @@
+use clap::{builder::PossibleValue, ValueEnum};
+use colored::Colorize;
+use regex::Regex;
+use rosesong::{bili_client::BiliClient, error::AppError};
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+/// 搜索结果的排序方式
+#[derive(Clone, Copy, Default)]
+pub enum SearchOrder {
+    /// 综合排序
+    #[default]
+    Default,
+    /// 最多播放
+    Click,
+    /// 最新发布
+    Pubdate,
+    /// 最多弹幕
+    Danmaku,
+    /// 最多收藏
+    Favorite,
+}
+
+impl SearchOrder {
+    fn as_param(self) -> &'static str {
+        match self {
+            SearchOrder::Default => "totalrank",
+            SearchOrder::Click => "click",
+            SearchOrder::Pubdate => "pubdate",
+            SearchOrder::Danmaku => "dm",
+            SearchOrder::Favorite => "stow",
+        }
+    }
+}
+
+impl ValueEnum for SearchOrder {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            SearchOrder::Default,
+            SearchOrder::Click,
+            SearchOrder::Pubdate,
+            SearchOrder::Danmaku,
+            SearchOrder::Favorite,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            SearchOrder::Default => PossibleValue::new("default").help("综合排序"),
+            SearchOrder::Click => PossibleValue::new("click").help("最多播放"),
+            SearchOrder::Pubdate => PossibleValue::new("pubdate").help("最新发布"),
+            SearchOrder::Danmaku => PossibleValue::new("danmaku").help("最多弹幕"),
+            SearchOrder::Favorite => PossibleValue::new("favorite").help("最多收藏"),
+        })
+    }
+}
+
+/// 按视频时长筛选
+#[derive(Clone, Copy)]
+pub enum SearchDuration {
+    /// 10 分钟以下
+    Short,
+    /// 10~30 分钟
+    Medium,
+    /// 30~60 分钟
+    Long,
+    /// 60 分钟以上
+    Longest,
+}
+
+impl SearchDuration {
+    fn as_param(self) -> &'static str {
+        match self {
+            SearchDuration::Short => "1",
+            SearchDuration::Medium => "2",
+            SearchDuration::Long => "3",
+            SearchDuration::Longest => "4",
+        }
+    }
+}
+
+impl ValueEnum for SearchDuration {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            SearchDuration::Short,
+            SearchDuration::Medium,
+            SearchDuration::Long,
+            SearchDuration::Longest,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            SearchDuration::Short => PossibleValue::new("short").help("10 分钟以下"),
+            SearchDuration::Medium => PossibleValue::new("medium").help("10~30 分钟"),
+            SearchDuration::Long => PossibleValue::new("long").help("30~60 分钟"),
+            SearchDuration::Longest => PossibleValue::new("longest").help("60 分钟以上"),
+        })
+    }
+}
+
+/// 搜索的筛选条件
+#[derive(Default)]
+pub struct SearchFilter {
+    pub order: SearchOrder,
+    pub duration: Option<SearchDuration>,
+    /// 分区 ID, 音乐区为 3
+    pub tid: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct SearchVideo {
+    pub bvid: String,
+    pub title: String,
+    pub author: String,
+    /// `分:秒` 格式的时长
+    pub duration: String,
+    #[serde(default)]
+    pub play: u64,
+}
+
+impl SearchVideo {
+    pub fn to_println_string(&self) -> String {
+        format!(
+            "{} {}, {} {}, {} {}, {} {}",
+            "title:".black(),
+            self.title.cyan(),
+            "owner:".black(),
+            self.author,
+            "duration:".black(),
+            self.duration,
+            "play:".black(),
+            format_count(self.play)
+        )
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SearchPage {
+    pub page: u32,
+    #[serde(rename = "numPages", default)]
+    pub num_pages: u32,
+    #[serde(default)]
+    pub result: Vec<SearchVideo>,
+}
+
+// 播放量超过一万时以万为单位显示
+fn format_count(count: u64) -> String {
+    if count >= 10_000 {
+        format!("{:.1}万", count as f64 / 10_000.0)
+    } else {
+        count.to_string()
+    }
+}
+
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+// 搜索结果的标题中带有高亮关键词的 html 标签和转义字符
+fn clean_title(title: &str) -> String {
+    HTML_TAG
+        .replace_all(title, "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// 按关键词搜索视频, page 从 1 开始, 每页 20 个结果
+pub async fn search_videos(
+    client: &BiliClient,
+    keyword: &str,
+    filter: &SearchFilter,
+    page: u32,
+) -> Result<SearchPage, AppError> {
+    let url = "https://api.bilibili.com/x/web-interface/wbi/search/type";
+    let page_str = page.to_string();
+    let tid = filter.tid.map(|tid| tid.to_string());
+    let mut params = vec![
+        ("search_type", "video"),
+        ("keyword", keyword),
+        ("page", page_str.as_str()),
+        ("order", filter.order.as_param()),
+    ];
+    if let Some(duration) = filter.duration {
+        params.push(("duration", duration.as_param()));
+    }
+    if let Some(tid) = &tid {
+        params.push(("tids", tid.as_str()));
+    }
+    let mut search_page: SearchPage = client.get_wbi(url, &params).await?;
+    for video in &mut search_page.result {
+        video.title = clean_title(&video.title);
+    }
+    Ok(search_page)
+}
@@ -1,19 +1,39 @@
+mod art;
 mod bilibili;
 
-use bilibili::fetch_audio_info::get_tracks;
+use bilibili::fetch_audio_info::{
+    check_availability, confirm, fetch_source, get_tracks, print_failures, resolve_entries,
+    RemoteEntry,
+};
+use bilibili::login::{generate_qrcode, logout, poll_qrcode, QrStatus};
+use bilibili::search::{search_videos, SearchDuration, SearchFilter, SearchOrder, SearchVideo};
 use clap::builder::PossibleValue;
 use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::{generate, Shell};
 use colored::Colorize;
+use qrcode::render::unicode;
+use qrcode::QrCode;
+use rosesong::bili_client::BiliClient;
+use rosesong::config::{load_config, save_config, Config};
+use rosesong::cover::cache_cover;
 use rosesong::error::AppError;
-use rosesong::model::{Playlist, Track};
+use rosesong::history::{get_play_records, play_counts, PlayEvent, PlayStats};
+use rosesong::import_target::{parse_import_target, resolve_import_target, ImportTarget};
+use rosesong::lyrics::{load_lyrics, Lyrics};
+use rosesong::model::{
+    CollectionKind, Playlist, PlaylistChange, Source, SourceImport, SourceKind, Track, TrackField,
+    TrackId, TrackPatch, TrackRemoval, TrackSelection, UnavailableReason,
+};
+use rosesong::session::{load_session, remove_session, save_session, Session};
 use rosesong::utils::{
-    get_current_play_info, get_playlist, init_dir, is_playlist_empty, playlist_file,
-    save_playlist_to_file,
+    config_file, format_duration, get_current_play_info, get_playlist, get_smart_playlists,
+    init_dir, is_playlist_empty, migrate_legacy_dirs, smart_playlist_file, update_playlist,
+    LegacyMigration,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::io::Write;
+use std::time::Duration;
 use tokio::{fs, io::AsyncBufReadExt, process::Command};
 use zbus::{proxy, Connection};
 
@@ -26,8 +46,11 @@ type StdResult<T> = std::result::Result<T, AppError>;
 )]
 trait MyPlayer {
     async fn play(&self) -> zbus::Result<()>;
-    async fn play_bvid(&self, bvid: &str) -> zbus::Result<()>;
+    async fn play_track(&self, id: &TrackId) -> zbus::Result<()>;
     async fn play_sid(&self, sid: &str) -> zbus::Result<()>;
+    async fn play_series(&self, id: &str) -> zbus::Result<()>;
+    async fn play_tag(&self, tag: &str) -> zbus::Result<()>;
+    async fn play_smart(&self, name: &str) -> zbus::Result<()>;
     async fn play_all(&self) -> zbus::Result<()>;
     async fn pause(&self) -> zbus::Result<()>;
     async fn next(&self) -> zbus::Result<()>;
@@ -35,9 +58,15 @@ trait MyPlayer {
     async fn stop(&self) -> zbus::Result<()>;
     async fn set_volume(&self, vol: &str) -> zbus::Result<()>;
     async fn set_mode(&self, mode: &str) -> zbus::Result<()>;
-    async fn playlist_change(&self) -> zbus::Result<()>;
     async fn test_connection(&self) -> zbus::Result<()>;
-    async fn playlist_is_empty(&self) -> zbus::Result<()>;
+    async fn add_tracks(&self, tracks: &str) -> zbus::Result<u32>;
+    async fn import_source(&self, import: &str) -> zbus::Result<u32>;
+    async fn update_tracks(&self, patches: &str) -> zbus::Result<u32>;
+    async fn remove_tracks(&self, removal: &str) -> zbus::Result<u32>;
+    async fn apply_changes(&self, changes: &str) -> zbus::Result<u32>;
+    async fn list_tracks(&self) -> zbus::Result<String>;
+    async fn position(&self) -> zbus::Result<(TrackId, f64)>;
+    async fn current_lyric(&self) -> zbus::Result<String>;
 }
 
 #[derive(Parser)]
@@ -112,37 +141,94 @@ enum Commands {
     #[command(about = "在歌曲列表中查找歌曲")]
     Find(FindCommand),
 
+    #[command(about = "在 B 站搜索视频, 添加或播放搜索结果")]
+    Search(SearchCommand),
+
     #[command(about = "从歌曲列表中删除歌曲")]
     Delete(DeleteCommand),
 
     #[command(about = "显示歌曲列表")]
     List(ListCommand),
 
-    #[command(about = "更新所有合集")]
+    #[command(about = "同步所有导入来源, 同 rsg sync")]
     Update,
 
+    #[command(about = "同步导入的收藏夹、合集、系列和 UP 主的投稿")]
+    Sync(SyncCommand),
+
     #[command(about = "启动 RoseSong")]
     Start,
 
+    #[command(about = "扫码登录 B 站账号")]
+    Login,
+
+    #[command(about = "退出登录")]
+    Logout,
+
+    #[command(about = "显示当前登录的账号")]
+    Whoami,
+
     #[command(about = "显示当前播放的歌曲信息")]
-    Status,
+    Status(StatusCommand),
+
+    #[command(about = "给歌曲评分")]
+    Rate(RateCommand),
+
+    #[command(about = "标记喜欢的歌曲")]
+    Love(LoveCommand),
+
+    #[command(about = "管理歌曲标签")]
+    Tag(TagCommand),
+
+    #[command(about = "查看智能列表")]
+    Smart(SmartCommand),
+
+    #[command(about = "显示最近的播放记录")]
+    History(HistoryCommand),
+
+    #[command(about = "显示收听统计")]
+    Stats(StatsCommand),
+
+    #[command(about = "显示歌曲的歌词 (CC 字幕)")]
+    Lyrics(LyricsCommand),
+
+    #[command(about = "检查歌曲列表中已失效的视频")]
+    Doctor(DoctorCommand),
+
+    #[command(about = "查看或修改配置")]
+    Config(ConfigCommand),
 }
 
 #[derive(Parser)]
 struct PlayCommand {
+    #[arg(help = "要播放的 B 站链接、短链接、bvid 或 av 号")]
+    target: Option<String>,
     #[arg(short = 'a', long = "all", action = clap::ArgAction::SetTrue, help = "播放全部歌曲")]
     all: bool,
-    #[arg(short = 'b', long = "bvid", help = "要播放的 bvid")]
+    #[arg(short = 'b', long = "bvid", help = "要播放的 bvid 或 av 号")]
     bvid: Option<String>,
+    #[arg(
+        short = 'c',
+        long = "cid",
+        requires = "bvid",
+        help = "要播放的分 P 的 cid, 默认为第一个分 P"
+    )]
+    cid: Option<String>,
     #[arg(short = 's', long = "sid", help = "要播放的合集 ID")]
     sid: Option<String>,
+    #[arg(long = "series", help = "要播放的系列 ID")]
+    series: Option<String>,
+    #[arg(short = 't', long = "tag", help = "只播放带有该标签的歌曲")]
+    tag: Option<String>,
+    #[arg(short = 'm', long = "smart", help = "播放指定的智能列表")]
+    smart: Option<String>,
 }
 
 #[derive(Parser)]
 struct VolumeCommand {
-    #[arg(short = 'u', long = "up", action = clap::ArgAction::SetTrue, help = "音量增加 5%")]
+    #[arg(short = 'u', long = "up", action = clap::ArgAction::SetTrue, help = "增加音量, 按 player.volume_step 调整")]
     up: bool,
-    #[arg(short = 'd', long = "down", action = clap::ArgAction::SetTrue, help = "音量减少 5%")]
+    #[arg(short = 'd', long = "down", action = clap::ArgAction::SetTrue, help = "减少音量, 按 player.volume_step 调整")]
     down: bool,
     #[arg(short = 'v', long = "value", help = "设置音量大小 [0~100]")]
     value: Option<usize>,
@@ -156,38 +242,95 @@ struct ModeCommand {
     shuffle_mode: bool,
     #[arg(short = 'r', long = "repeat", action = clap::ArgAction::SetTrue, help = "设置播放模式为单曲循环")]
     repeat_mode: bool,
+    #[arg(short = 'o', long = "once", action = clap::ArgAction::SetTrue, help = "设置播放模式为顺序播放, 播放完列表后停止")]
+    sequential_mode: bool,
+    #[arg(short = 'S', long = "shuffle-seasons", action = clap::ArgAction::SetTrue, help = "设置播放模式为合集随机, 合集内按顺序播放")]
+    shuffle_seasons_mode: bool,
+    #[arg(short = 'w', long = "weighted", action = clap::ArgAction::SetTrue, help = "设置播放模式为加权随机, 降低最近播放歌曲的概率")]
+    weighted_mode: bool,
 }
 
 #[derive(Parser)]
 struct AddCommand {
+    #[arg(help = "要导入的 B 站链接、短链接、bvid 或 av 号")]
+    target: Option<String>,
     #[arg(short = 'f', long = "fid", help = "要导入的收藏夹 ID")]
     fid: Option<String>,
-    #[arg(short = 'b', long = "bvid", help = "要导入的 bvid")]
+    #[arg(short = 'b', long = "bvid", help = "要导入的 bvid 或 av 号")]
     bvid: Option<String>,
     #[arg(short = 's', long = "sid", help = "要导入的合集 ID")]
     sid: Option<String>,
+    #[arg(long = "series", help = "要导入的系列 (列表) ID")]
+    series: Option<String>,
+    #[arg(
+        short = 'm',
+        long = "mid",
+        help = "要导入全部投稿的 UP 主 uid, 之后 rsg update 会导入新投稿"
+    )]
+    mid: Option<String>,
+    #[arg(short = 'k', long = "keyword", help = "只导入标题包含该关键词的投稿")]
+    keyword: Option<String>,
+    #[arg(long = "since", help = "只导入该日期之后的投稿, 格式为 YYYY-MM-DD")]
+    since: Option<String>,
 }
 
 #[derive(Parser)]
 struct FindCommand {
     #[arg(short = 'b', long = "bvid", help = "按 bvid 查找")]
     bvid: Option<String>,
-    // #[arg(short = 'c', long = "cid", help = "按 cid 查找")]
-    // cid: Option<String>,
+    #[arg(short = 'c', long = "cid", help = "按 cid 查找")]
+    cid: Option<String>,
     #[arg(short = 't', long = "title", help = "按标题查找")]
     title: Option<String>,
     #[arg(short = 'o', long = "owner", help = "按作者查找")]
     owner: Option<String>,
+    #[arg(long = "tag", help = "按标签查找")]
+    tag: Option<String>,
+    #[arg(short = 'r', long = "rating", help = "查找评分不低于该值的歌曲")]
+    rating: Option<u8>,
+    #[arg(short = 'l', long = "loved", action = clap::ArgAction::SetTrue, help = "只查找喜欢的歌曲")]
+    loved: bool,
+}
+
+#[derive(Parser)]
+struct SearchCommand {
+    #[arg(help = "搜索关键词")]
+    keyword: String,
+    #[arg(short = 'd', long = "duration", value_enum, help = "按视频时长筛选")]
+    duration: Option<SearchDuration>,
+    #[arg(short = 't', long = "tid", help = "按分区 ID 筛选, 音乐区为 3")]
+    tid: Option<u32>,
+    #[arg(
+        short = 'o',
+        long = "order",
+        value_enum,
+        default_value = "default",
+        help = "排序方式"
+    )]
+    order: SearchOrder,
 }
 
 #[derive(Parser)]
 struct DeleteCommand {
     #[arg(short = 'b', long = "bvid", help = "按 bvid 删除")]
     bvid: Option<String>,
+    #[arg(
+        short = 'c',
+        long = "cid",
+        requires = "bvid",
+        help = "只删除该 bvid 下指定 cid 的分 P"
+    )]
+    cid: Option<String>,
     #[arg(short = 's', long = "sid", help = "按合集 ID 删除")]
     sid: Option<String>,
+    #[arg(long = "series", help = "按系列 ID 删除")]
+    series: Option<String>,
     #[arg(short = 'o', long = "owner", help = "按作者删除")]
     owner: Option<String>,
+    #[arg(short = 'f', long = "fid", help = "删除导入的收藏夹及其中的歌曲")]
+    fid: Option<String>,
+    #[arg(short = 'm', long = "mid", help = "取消关注该 UP 主并删除其投稿")]
+    mid: Option<String>,
     #[arg(short = 'a', long = "all", help = "删除所有曲目")]
     all: bool,
 }
@@ -196,10 +339,221 @@ struct DeleteCommand {
 struct ListCommand {
     #[arg(short = 's', action = clap::ArgAction::SetTrue, help = "显示所有合集")]
     season: bool,
+    #[arg(short = 'u', action = clap::ArgAction::SetTrue, help = "显示所有导入来源")]
+    sources: bool,
+}
+
+#[derive(Parser)]
+struct SyncCommand {
+    #[arg(short = 'n', long = "dry-run", action = clap::ArgAction::SetTrue, help = "只显示变化, 不修改歌曲列表")]
+    dry_run: bool,
+}
+
+#[derive(Parser)]
+struct StatusCommand {
+    #[arg(long = "art", action = clap::ArgAction::SetTrue, help = "在终端中显示当前歌曲的封面 (需要终端支持 kitty 或 sixel 图片协议)")]
+    art: bool,
+}
+
+#[derive(Parser)]
+struct RateCommand {
+    #[arg(help = "评分 [1~5], 0 表示清除评分")]
+    rating: u8,
+    #[arg(
+        short = 'b',
+        long = "bvid",
+        help = "要评分的 bvid, 默认为当前播放的歌曲"
+    )]
+    bvid: Option<String>,
+    #[arg(
+        short = 'c',
+        long = "cid",
+        requires = "bvid",
+        help = "只给该 bvid 下指定 cid 的分 P 评分"
+    )]
+    cid: Option<String>,
+}
+
+#[derive(Parser)]
+struct LoveCommand {
+    #[arg(
+        short = 'b',
+        long = "bvid",
+        help = "要标记的 bvid, 默认为当前播放的歌曲"
+    )]
+    bvid: Option<String>,
+    #[arg(
+        short = 'c',
+        long = "cid",
+        requires = "bvid",
+        help = "只标记该 bvid 下指定 cid 的分 P"
+    )]
+    cid: Option<String>,
+    #[arg(short = 'u', long = "undo", action = clap::ArgAction::SetTrue, help = "取消喜欢")]
+    undo: bool,
+}
+
+#[derive(Parser)]
+struct TagCommand {
+    #[command(subcommand)]
+    action: TagAction,
+}
+
+#[derive(Subcommand)]
+enum TagAction {
+    #[command(about = "给歌曲添加标签")]
+    Add {
+        #[arg(required = true, help = "要添加的标签")]
+        tags: Vec<String>,
+        #[arg(
+            short = 'b',
+            long = "bvid",
+            help = "要添加标签的 bvid, 默认为当前播放的歌曲"
+        )]
+        bvid: Option<String>,
+        #[arg(
+            short = 'c',
+            long = "cid",
+            requires = "bvid",
+            help = "只给该 bvid 下指定 cid 的分 P 添加标签"
+        )]
+        cid: Option<String>,
+    },
+    #[command(about = "删除歌曲的标签")]
+    Rm {
+        #[arg(required = true, help = "要删除的标签")]
+        tags: Vec<String>,
+        #[arg(
+            short = 'b',
+            long = "bvid",
+            help = "要删除标签的 bvid, 默认为当前播放的歌曲"
+        )]
+        bvid: Option<String>,
+        #[arg(
+            short = 'c',
+            long = "cid",
+            requires = "bvid",
+            help = "只删除该 bvid 下指定 cid 的分 P 的标签"
+        )]
+        cid: Option<String>,
+    },
+    #[command(about = "显示所有标签")]
+    Ls,
+}
+
+#[derive(Parser)]
+struct SmartCommand {
+    #[arg(help = "要显示歌曲的智能列表名称, 不指定时显示所有智能列表")]
+    name: Option<String>,
+}
+
+#[derive(Parser)]
+struct DoctorCommand {
+    #[arg(short = 'c', long = "check", action = clap::ArgAction::SetTrue, help = "联网检查所有视频是否仍然可以播放")]
+    check: bool,
+    #[arg(short = 'p', long = "prune-unavailable", action = clap::ArgAction::SetTrue, help = "从歌曲列表中删除已失效的歌曲")]
+    prune_unavailable: bool,
+    #[arg(short = 'y', long = "yes", action = clap::ArgAction::SetTrue, help = "删除前不再确认")]
+    yes: bool,
+}
+
+#[derive(Parser)]
+struct ConfigCommand {
+    #[command(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    #[command(about = "显示配置项的值, 不指定名称时显示所有配置")]
+    Get {
+        #[arg(help = "配置项名称, 如 player.volume_step")]
+        key: Option<String>,
+    },
+    #[command(about = "修改配置项")]
+    Set {
+        #[arg(help = "配置项名称, 如 player.volume_step")]
+        key: String,
+        #[arg(help = "新的值")]
+        value: String,
+    },
+    #[command(about = "使用 $EDITOR 编辑配置文件")]
+    Edit,
+    #[command(about = "显示配置文件的路径")]
+    Path,
+}
+
+#[derive(Parser)]
+struct HistoryCommand {
+    #[arg(
+        short = 'n',
+        long = "number",
+        default_value_t = 50,
+        help = "显示的记录条数"
+    )]
+    number: usize,
+}
+
+#[derive(Parser)]
+struct StatsCommand {
+    #[arg(
+        short = 'n',
+        long = "top",
+        default_value_t = 10,
+        help = "显示前 N 首歌曲和 up 主"
+    )]
+    top: usize,
+    #[arg(
+        short = 'd',
+        long = "days",
+        default_value_t = 7,
+        help = "按天统计最近 N 天"
+    )]
+    days: usize,
+    #[arg(
+        short = 'w',
+        long = "weeks",
+        default_value_t = 4,
+        help = "按周统计最近 N 周"
+    )]
+    weeks: usize,
+    #[arg(short = 'u', long = "unplayed", action = clap::ArgAction::SetTrue, help = "列出从未完整播放过的歌曲")]
+    unplayed: bool,
+}
+
+#[derive(Parser)]
+struct LyricsCommand {
+    #[arg(
+        short = 'b',
+        long = "bvid",
+        help = "要显示歌词的 bvid, 默认为当前播放的歌曲"
+    )]
+    bvid: Option<String>,
+    #[arg(
+        short = 'f',
+        long = "follow",
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["bvid", "export"],
+        help = "跟随播放进度滚动显示歌词"
+    )]
+    follow: bool,
+    #[arg(short = 'e', long = "export", help = "将歌词导出为 LRC 文件")]
+    export: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> StdResult<()> {
+    for migration in migrate_legacy_dirs()? {
+        match migration {
+            LegacyMigration::Moved { from, to } => {
+                println!("已将 {} 移动到 {}", from.display(), to.display());
+            }
+            LegacyMigration::Skipped { from, to } => println!(
+                "{}",
+                format!("{} 已经存在, 没有迁移 {}", to.display(), from.display()).yellow()
+            ),
+        }
+    }
     init_dir().await?;
     let cli = Cli::parse();
     let connection = Connection::session().await?;
@@ -228,14 +582,28 @@ async fn handle_command(cli: Cli, proxy: MyPlayerProxy<'_>) -> StdResult<()> {
             Commands::Mode(mode_cmd) => handle_mode_command(mode_cmd, &proxy).await,
             Commands::Add(add_cmd) => handle_add_command(add_cmd, &proxy).await,
             Commands::Delete(del_cmd) => handle_delete_command(del_cmd, &proxy).await,
-            Commands::Find(find_cmd) => handle_find_command(find_cmd).await,
-            Commands::List(list_cmd) => display_playlist(list_cmd).await,
-            Commands::Update => update_season(&proxy).await,
+            Commands::Find(find_cmd) => handle_find_command(find_cmd, &proxy).await,
+            Commands::Search(search_cmd) => handle_search_command(search_cmd, &proxy).await,
+            Commands::List(list_cmd) => display_playlist(list_cmd, &proxy).await,
+            Commands::Update => sync_sources(SyncCommand { dry_run: false }, &proxy).await,
+            Commands::Sync(sync_cmd) => sync_sources(sync_cmd, &proxy).await,
             Commands::Start => start_rosesong(&proxy).await,
-            Commands::Status => display_status(&proxy).await,
+            Commands::Login => handle_login_command(&proxy).await,
+            Commands::Logout => handle_logout_command(&proxy).await,
+            Commands::Whoami => display_whoami().await,
+            Commands::Status(status_cmd) => display_status(status_cmd, &proxy).await,
+            Commands::Rate(rate_cmd) => handle_rate_command(rate_cmd, &proxy).await,
+            Commands::Love(love_cmd) => handle_love_command(love_cmd, &proxy).await,
+            Commands::Tag(tag_cmd) => handle_tag_command(tag_cmd, &proxy).await,
+            Commands::Smart(smart_cmd) => display_smart_playlists(smart_cmd).await,
+            Commands::History(history_cmd) => display_history(history_cmd).await,
+            Commands::Stats(stats_cmd) => display_stats(stats_cmd).await,
+            Commands::Lyrics(lyrics_cmd) => handle_lyrics_command(lyrics_cmd, &proxy).await,
+            Commands::Doctor(doctor_cmd) => handle_doctor_command(doctor_cmd, &proxy).await,
+            Commands::Config(config_cmd) => handle_config_command(config_cmd).await,
         }
     } else {
-        display_status(&proxy).await
+        display_status(StatusCommand { art: false }, &proxy).await
     }
 }
 
@@ -244,12 +612,72 @@ async fn handle_play_command(play_cmd: PlayCommand, proxy: &MyPlayerProxy<'_>) -
         println!("{}", "rosesong 没有处于运行状态".red());
     } else if is_playlist_empty().await? {
         println!("{}", "当前歌曲列表为空，请先添加歌曲".red());
+    } else if let Some(input) = play_cmd.target {
+        let client = BiliClient::new()?;
+        match resolve_import_target(client.http(), &input).await? {
+            ImportTarget::Video { bvid, page } => {
+                let playlist = get_playlist().await?;
+                let id = match page {
+                    Some(page) => playlist
+                        .tracks
+                        .iter()
+                        .find(|t| t.bvid == bvid && t.page.unwrap_or(1) == page)
+                        .map(Track::id),
+                    None => playlist.resolve_track_id(&bvid, None),
+                };
+                if let Some(id) = id {
+                    proxy.play_track(&id).await?;
+                    println!("播放指定 bvid");
+                } else {
+                    println!("{}", format!("歌曲列表中没有找到 {bvid}").red());
+                }
+            }
+            ImportTarget::Season { sid } => {
+                proxy.play_sid(&sid).await?;
+                println!("播放指定合集");
+            }
+            ImportTarget::Series { sid } => {
+                proxy.play_series(&sid).await?;
+                println!("播放指定系列");
+            }
+            target => {
+                println!(
+                    "{}",
+                    format!("{target} 无法直接播放，请先使用 rsg add 导入").red()
+                );
+            }
+        }
     } else if let Some(bvid) = play_cmd.bvid {
-        proxy.play_bvid(&bvid).await?;
-        println!("播放指定 bvid");
+        let bvid = normalize_bvid(&bvid)?;
+        let playlist = get_playlist().await?;
+        if let Some(id) = playlist.resolve_track_id(&bvid, play_cmd.cid.as_deref()) {
+            proxy.play_track(&id).await?;
+            println!("播放指定 bvid");
+        } else {
+            println!("{}", format!("歌曲列表中没有找到 {bvid}").red());
+        }
     } else if let Some(sid) = play_cmd.sid {
         proxy.play_sid(&sid).await?;
         println!("播放指定合集");
+    } else if let Some(id) = play_cmd.series {
+        proxy.play_series(&id).await?;
+        println!("播放指定系列");
+    } else if let Some(tag) = play_cmd.tag {
+        let playlist = get_playlist().await?;
+        if playlist.find_tracks_with_tag(&tag).is_empty() {
+            println!("{}", format!("没有带有标签 [{tag}] 的歌曲").red());
+        } else {
+            proxy.play_tag(&tag).await?;
+            println!("播放标签 [{tag}] 下的歌曲");
+        }
+    } else if let Some(name) = play_cmd.smart {
+        let playlist = get_playlist_with_smart().await?;
+        if playlist.find_tracks_in_smart(&name).is_empty() {
+            println!("{}", format!("智能列表 [{name}] 不存在或没有歌曲").red());
+        } else {
+            proxy.play_smart(&name).await?;
+            println!("播放智能列表 [{name}]");
+        }
     } else if play_cmd.all {
         proxy.play_all().await?;
         println!("播放全部歌曲");
@@ -311,12 +739,16 @@ async fn handle_volume_command(vol_cmd: VolumeCommand, proxy: &MyPlayerProxy<'_>
         println!("{}", "rosesong 没有处于运行状态".red());
     } else if is_playlist_empty().await? {
         println!("{}", "当前歌曲列表为空，请先添加歌曲".red());
-    } else if vol_cmd.up {
-        proxy.set_volume("up").await?;
-        println!("增加 5% 音量");
-    } else if vol_cmd.down {
-        proxy.set_volume("down").await?;
-        println!("减少 5% 音量");
+    } else if vol_cmd.up || vol_cmd.down {
+        // 仅用于显示, 配置文件有误时按默认值显示
+        let step = load_config().unwrap_or_default().player.volume_step;
+        if vol_cmd.up {
+            proxy.set_volume("up").await?;
+            println!("增加 {step}% 音量");
+        } else {
+            proxy.set_volume("down").await?;
+            println!("减少 {step}% 音量");
+        }
     } else if let Some(value) = vol_cmd.value {
         if value > 100 {
             println!("{}", "音量不能超过 100".red());
@@ -342,6 +774,15 @@ async fn handle_mode_command(mode_cmd: ModeCommand, proxy: &MyPlayerProxy<'_>) -
     } else if mode_cmd.repeat_mode {
         proxy.set_mode("Repeat").await?;
         println!("设置为单曲循环");
+    } else if mode_cmd.sequential_mode {
+        proxy.set_mode("Sequential").await?;
+        println!("设置为顺序播放");
+    } else if mode_cmd.shuffle_seasons_mode {
+        proxy.set_mode("ShuffleSeasons").await?;
+        println!("设置为合集随机");
+    } else if mode_cmd.weighted_mode {
+        proxy.set_mode("Weighted").await?;
+        println!("设置为加权随机");
     } else {
         println!("{}", "没有这个播放模式".red());
     }
@@ -355,6 +796,109 @@ async fn is_rosesong_running(proxy: &MyPlayerProxy<'_>) -> StdResult<bool> {
     }
 }
 
+async fn handle_login_command(proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
+    let client = BiliClient::new()?;
+    let qrcode = generate_qrcode(&client).await?;
+    let code = QrCode::new(qrcode.url.as_bytes())
+        .map_err(|e| AppError::DataParsing(format!("生成二维码失败: {e}")))?;
+    let image = code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build();
+    println!("{image}");
+    println!("请使用哔哩哔哩手机客户端扫描二维码登录");
+    let mut scanned = false;
+    loop {
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        match poll_qrcode(&client, &qrcode.qrcode_key).await? {
+            QrStatus::Waiting => {}
+            QrStatus::Scanned => {
+                if !scanned {
+                    scanned = true;
+                    println!("已扫码，请在手机上确认登录");
+                }
+            }
+            QrStatus::Expired => {
+                println!("{}", "二维码已失效，请重新运行 rsg login".red());
+                return Ok(());
+            }
+            QrStatus::Confirmed(url) => {
+                let session = Session::from_login_url(&url).ok_or_else(|| {
+                    AppError::DataParsing("登录返回的数据中缺少 cookie".to_string())
+                })?;
+                save_session(&session).await?;
+                // 使用新的登录信息重新创建客户端
+                let nav = BiliClient::new()?.nav().await?;
+                println!("{}", format!("登录成功：{}", nav.uname).green());
+                clear_needs_login(proxy).await?;
+                if is_rosesong_running(proxy).await? {
+                    println!("{}", "重启 rosesong 后播放器才会使用新的登录信息".yellow());
+                }
+                return Ok(());
+            }
+        }
+    }
+}
+
+// 旧版本在登录失效时把歌曲标记为需要登录, 登录成功后清除这些标记
+async fn clear_needs_login(proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
+    let patches: Vec<TrackPatch> = get_playlist()
+        .await?
+        .tracks
+        .iter()
+        .filter(|t| {
+            t.unavailable
+                .as_ref()
+                .is_some_and(|u| u.reason == UnavailableReason::NeedsLogin)
+        })
+        .map(|t| TrackPatch {
+            id: t.id(),
+            fields: vec![TrackField::Unavailable(None)],
+        })
+        .collect();
+    if !patches.is_empty() {
+        let count = change_playlist(PlaylistChange::UpdateTracks(patches), proxy).await?;
+        println!("已恢复 {count} 首之前因未登录而无法播放的歌曲");
+    }
+    Ok(())
+}
+
+async fn handle_logout_command(proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
+    let Some(session) = load_session() else {
+        println!("当前未登录");
+        return Ok(());
+    };
+    if let Err(e) = logout(&BiliClient::new()?, &session.bili_jct).await {
+        eprintln!("{}", format!("退出登录请求失败：{e}").red());
+    }
+    remove_session().await?;
+    println!("{}", "已退出登录".green());
+    if is_rosesong_running(proxy).await? {
+        println!("{}", "重启 rosesong 后播放器才会清除登录信息".yellow());
+    }
+    Ok(())
+}
+
+async fn display_whoami() -> StdResult<()> {
+    if load_session().is_none() {
+        println!("未登录，请使用 rsg login 登录");
+        return Ok(());
+    }
+    let nav = BiliClient::new()?.nav().await?;
+    if nav.is_login {
+        let vip = if nav.vip_status == 1 {
+            " [大会员]"
+        } else {
+            ""
+        };
+        println!("{} (uid: {}){}", nav.uname.green(), nav.mid, vip);
+    } else {
+        println!("{}", "登录已失效，请使用 rsg login 重新登录".red());
+    }
+    Ok(())
+}
+
 async fn start_rosesong(proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
     if is_rosesong_running(proxy).await? {
         println!("{}", "RoseSong 当前已经处于运行状态".yellow());
@@ -385,36 +929,64 @@ async fn start_rosesong(proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
     Ok(())
 }
 
-async fn reload_playlist(proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
-    let is_running = is_rosesong_running(proxy).await?;
-    if is_running {
-        if is_playlist_empty().await? {
-            proxy.playlist_is_empty().await?;
-        } else {
-            proxy.playlist_change().await?;
-        }
-    } else if let Some(mut cur_play_info) = get_current_play_info().await {
-        if let Some(cur_track) = cur_play_info.get_current_track() {
-            let playlist = get_playlist().await.unwrap_or_default();
-            if let Some(sid) = cur_play_info.playing_sid.as_ref() {
-                let tracks = playlist.find_tracks_in_season(sid);
-                cur_play_info.current_tracks = tracks;
-            } else {
-                cur_play_info.current_tracks = playlist.tracks;
+fn to_json(value: &impl Serialize) -> StdResult<String> {
+    serde_json::to_string(value).map_err(|e| AppError::DataParsing(e.to_string()))
+}
+
+/// rosesong 运行时由它修改歌曲列表并更新播放列表, 否则直接修改歌曲列表文件,
+/// 返回受影响的数量
+async fn change_playlist(change: PlaylistChange, proxy: &MyPlayerProxy<'_>) -> StdResult<usize> {
+    if is_rosesong_running(proxy).await? {
+        let count = match &change {
+            PlaylistChange::ImportSource(import)
+                if import.season.is_none() && import.source.is_none() =>
+            {
+                proxy.add_tracks(&to_json(&import.tracks)?).await?
             }
-            let cur_bvid = cur_track.bvid;
-            let new_index = cur_play_info
-                .current_tracks
-                .clone()
-                .iter()
-                .position(|t| t.bvid == cur_bvid);
-            match new_index {
-                Some(new_index) => {
-                    cur_play_info.set_current(new_index).await?;
-                }
-                None => {
-                    cur_play_info.set_current(cur_play_info.index).await?;
-                }
+            PlaylistChange::ImportSource(import) => {
+                proxy.import_source(&to_json(import.as_ref())?).await?
+            }
+            PlaylistChange::UpdateTracks(patches) => {
+                proxy.update_tracks(&to_json(patches)?).await?
+            }
+            PlaylistChange::RemoveTracks(removal) => {
+                proxy.remove_tracks(&to_json(removal)?).await?
+            }
+            PlaylistChange::Batch(changes) => proxy.apply_changes(&to_json(changes)?).await?,
+        };
+        return Ok(count as usize);
+    }
+    let count = update_playlist(move |playlist| playlist.apply(change)).await?;
+    refresh_current_play_info().await?;
+    Ok(count)
+}
+
+/// 列出歌曲列表中的歌曲, rosesong 运行时从 rosesong 获取
+async fn list_tracks(proxy: &MyPlayerProxy<'_>) -> StdResult<Vec<Track>> {
+    if is_rosesong_running(proxy).await? {
+        let tracks = proxy.list_tracks().await?;
+        return serde_json::from_str(&tracks).map_err(|e| AppError::DataParsing(e.to_string()));
+    }
+    Ok(get_playlist().await?.tracks)
+}
+
+// rosesong 没有运行时, 按照新的歌曲列表更新保存的播放状态
+async fn refresh_current_play_info() -> StdResult<()> {
+    let mut cur_play_info = get_current_play_info().await?;
+    if let Some(cur_track) = cur_play_info.get_current_track() {
+        let playlist = get_playlist_with_smart().await?;
+        let tracks = cur_play_info.select_tracks(&playlist);
+        cur_play_info.replace_tracks(tracks);
+        let new_index = cur_play_info
+            .current_tracks
+            .iter()
+            .position(|t| t.has_id(&cur_track.id()));
+        match new_index {
+            Some(new_index) => {
+                cur_play_info.set_current(new_index).await?;
+            }
+            None => {
+                cur_play_info.set_current(cur_play_info.index).await?;
             }
         }
     }
@@ -422,69 +994,85 @@ async fn reload_playlist(proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
 }
 
 async fn handle_add_command(add_cmd: AddCommand, proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
-    let playlist_path = playlist_file()?;
-    let old_content = fs::read_to_string(&playlist_path).await.unwrap_or_default();
     println!("正在获取相关信息");
-    import_favorite_or_bvid_or_sid(add_cmd.fid, add_cmd.bvid, add_cmd.sid).await?;
-    println!("{}", "导入成功".green());
-    let new_content = fs::read_to_string(&playlist_path).await.unwrap_or_default();
-    if old_content != new_content {
-        reload_playlist(proxy).await?;
-    }
+    let client = BiliClient::new()?;
+    let target = if let Some(input) = add_cmd.target {
+        resolve_import_target(client.http(), &input).await?
+    } else if let Some(fid) = add_cmd.fid {
+        ImportTarget::Favorite { fid }
+    } else if let Some(bvid) = add_cmd.bvid {
+        resolve_import_target(client.http(), &bvid).await?
+    } else if let Some(sid) = add_cmd.sid {
+        ImportTarget::Season { sid }
+    } else if let Some(sid) = add_cmd.series {
+        ImportTarget::Series { sid }
+    } else if let Some(mid) = add_cmd.mid {
+        ImportTarget::Space {
+            mid,
+            keyword: None,
+            since: None,
+        }
+    } else {
+        return Err(AppError::InvalidInput(
+            "请提供链接或 fid 或 bvid 或 sid 或 mid".to_string(),
+        ));
+    };
+    let target = match target {
+        ImportTarget::Space { mid, .. } => ImportTarget::Space {
+            mid,
+            keyword: add_cmd.keyword,
+            since: add_cmd.since,
+        },
+        _ if add_cmd.keyword.is_some() || add_cmd.since.is_some() => {
+            return Err(AppError::InvalidInput(
+                "--keyword 和 --since 只能用于导入 UP 主的投稿".to_string(),
+            ));
+        }
+        target => target,
+    };
+    println!("导入{target}");
+    let added = import_target(&client, target, proxy).await?;
+    println!("{}", format!("导入成功, 新增 {added} 首歌曲").green());
     Ok(())
 }
 
-async fn import_favorite_or_bvid_or_sid(
-    fid: Option<String>,
-    bvid: Option<String>,
-    sid: Option<String>,
-) -> StdResult<()> {
-    let client = reqwest::Client::new();
-    let (new_tracks, new_season) = get_tracks(&client, fid, bvid, sid).await?;
-
-    let mut tracks = Vec::new();
-    let mut seasons = Vec::new();
-    let playlist = get_playlist().await;
-    if let Some(playlist) = playlist {
-        tracks.extend(playlist.tracks);
-        seasons.extend(playlist.seasons);
-    }
-    // update tracks
-    let new_tracks_bvid: Vec<String> = new_tracks.iter().map(|t| t.bvid.clone()).collect();
-    tracks.retain(|t| !new_tracks_bvid.contains(&t.bvid));
-    tracks.extend(new_tracks);
-    // update seasons
-    if let Some(new_season) = new_season {
-        seasons.retain(|s| s.id != new_season.id);
-        seasons.push(new_season);
-    }
-
-    let playlist = Playlist { tracks, seasons };
-    save_playlist_to_file(&playlist).await?;
-    Ok(())
+/// bvid 参数同时接受 av 号
+fn normalize_bvid(input: &str) -> StdResult<String> {
+    match parse_import_target(input)? {
+        ImportTarget::Video { bvid, .. } => Ok(bvid),
+        target => Err(AppError::InvalidInput(format!("{target} 不是视频"))),
+    }
 }
 
-async fn handle_delete_command(del_cmd: DeleteCommand, proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
-    let playlist_path = playlist_file()?;
-    if !playlist_path.exists() {
-        println!("{}", "歌曲列表文件不存在".red());
-        return Ok(());
-    }
-    let old_content = fs::read_to_string(&playlist_path).await.unwrap_or_default();
-    perform_deletion(del_cmd.bvid, del_cmd.sid, del_cmd.owner, del_cmd.all).await?;
-    let new_content = fs::read_to_string(&playlist_path).await.unwrap_or_default();
-    if old_content != new_content {
-        reload_playlist(proxy).await?;
-    }
-    Ok(())
+/// 获取导入目标中的歌曲并加入歌曲列表, 返回新增的歌曲数量
+async fn import_target(
+    client: &BiliClient,
+    target: ImportTarget,
+    proxy: &MyPlayerProxy<'_>,
+) -> StdResult<usize> {
+    let fetched = get_tracks(client, target).await?;
+    let import = SourceImport {
+        tracks: fetched.tracks,
+        season: fetched.season,
+        source: fetched.source,
+    };
+    let added = change_playlist(PlaylistChange::ImportSource(Box::new(import)), proxy).await?;
+    print_failures(&fetched.failures);
+    Ok(added)
 }
 
-async fn perform_deletion(
-    bvid: Option<String>,
-    sid: Option<String>,
-    owner: Option<String>,
-    all: bool,
-) -> StdResult<()> {
+async fn handle_delete_command(del_cmd: DeleteCommand, proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
+    let DeleteCommand {
+        bvid,
+        cid,
+        sid,
+        series,
+        owner,
+        fid,
+        mid,
+        all,
+    } = del_cmd;
+    let playlist = get_playlist().await?;
     if all {
         print!("即将清空歌曲列表，是否确认删除所有歌曲？[y/n]: ");
         std::io::stdout().flush().unwrap();
@@ -495,7 +1083,11 @@ async fn perform_deletion(
             .await
             .expect("Failed to read line");
         if confirmation.trim().eq_ignore_ascii_case("y") {
-            save_playlist_to_file(&Playlist::default()).await?;
+            let removal = TrackRemoval {
+                ids: playlist.tracks.iter().map(Track::id).collect(),
+                sources: playlist.sources,
+            };
+            change_playlist(PlaylistChange::RemoveTracks(removal), proxy).await?;
             println!("{}", "歌曲列表已清空".green());
         } else {
             println!("{}", "取消清空操作".yellow());
@@ -503,27 +1095,31 @@ async fn perform_deletion(
         return Ok(());
     }
 
-    let mut playlist = get_playlist().await.unwrap_or_default();
-    let mut tracks_to_delete: Vec<Track> = Vec::new();
+    let mut tracks_to_delete: HashSet<TrackId> = HashSet::new();
 
     // bvid
     if let Some(bvid) = bvid {
+        let bvid = normalize_bvid(&bvid)?;
         tracks_to_delete.extend(
             playlist
                 .tracks
                 .iter()
-                .filter(|track| track.bvid == bvid)
-                .cloned(),
+                .filter(|track| {
+                    track.bvid == bvid && cid.as_ref().is_none_or(|cid| &track.cid == cid)
+                })
+                .map(Track::id),
         );
     }
-    // sid
-    if sid.is_some() {
+    // sid or series
+    let collection = sid
+        .map(|sid| (CollectionKind::Season, sid))
+        .or(series.map(|id| (CollectionKind::Series, id)));
+    if let Some((kind, id)) = &collection {
         tracks_to_delete.extend(
             playlist
-                .tracks
+                .find_tracks_in_season(*kind, id)
                 .iter()
-                .filter(|track| track.sid == sid)
-                .cloned(),
+                .map(Track::id),
         );
     }
     // owner
@@ -533,16 +1129,56 @@ async fn perform_deletion(
                 .tracks
                 .iter()
                 .filter(|track| track.owner.contains(&owner))
-                .cloned(),
+                .map(Track::id),
+        );
+    }
+
+    // imported favourite or owner
+    let unfollow = fid
+        .map(|fid| (SourceKind::Favorite, fid))
+        .or(mid.map(|mid| (SourceKind::Owner, mid)))
+        .and_then(|(kind, id)| {
+            playlist
+                .sources
+                .iter()
+                .find(|s| s.kind == kind && s.id == id)
+                .cloned()
+        });
+    if let Some(source) = &unfollow {
+        // 只删除该来源记录的视频, 仍属于其他来源或合集的歌曲保留
+        let shared: HashSet<&String> = playlist
+            .sources
+            .iter()
+            .filter(|s| !s.is_same(source))
+            .flat_map(|s| &s.bvids)
+            .collect();
+        tracks_to_delete.extend(
+            playlist
+                .tracks
+                .iter()
+                .filter(|track| {
+                    source.bvids.contains(&track.bvid)
+                        && !shared.contains(&track.bvid)
+                        && track.collection().is_none()
+                })
+                .map(Track::id),
         );
     }
 
     if tracks_to_delete.is_empty() {
-        if let Some(sid) = sid {
-            println!("该合集下没有 track");
-            playlist.seasons.retain(|s| s.id != sid);
-            save_playlist_to_file(&playlist).await?;
-            println!("{}", "删除合集成功".green());
+        if let Some(source) = unfollow {
+            println!("该{}没有已导入的 track", source.kind);
+            let removal = TrackRemoval {
+                ids: Vec::new(),
+                sources: vec![source],
+            };
+            change_playlist(PlaylistChange::RemoveTracks(removal), proxy).await?;
+            println!("{}", "删除导入来源成功".green());
+        } else if let Some((kind, _)) = collection {
+            // 没有歌曲的合集和对应的来源会在删除时一并清理
+            println!("该{kind}下没有 track");
+            change_playlist(PlaylistChange::RemoveTracks(TrackRemoval::default()), proxy).await?;
+            println!("{}", format!("删除{kind}成功").green());
         } else {
             println!("{}", "没有找到符合条件的 track".black());
         }
@@ -560,20 +1196,12 @@ async fn perform_deletion(
         .await
         .expect("Failed to read line");
     if confirmation.trim().eq_ignore_ascii_case("y") {
-        // retain tracks
-        playlist
-            .tracks
-            .retain(|track| !tracks_to_delete.contains(track));
-        // retain seasons
-        let exists_seasons = playlist
-            .tracks
-            .iter()
-            .filter_map(|t| t.sid.clone())
-            .collect::<HashSet<String>>();
-        // retain seasons
-        playlist.seasons.retain(|s| exists_seasons.contains(&s.id));
-        // save to file
-        save_playlist_to_file(&playlist).await?;
+        // 没有歌曲的合集和对应的来源会一并删除
+        let removal = TrackRemoval {
+            ids: tracks_to_delete.into_iter().collect(),
+            sources: unfollow.into_iter().collect(),
+        };
+        change_playlist(PlaylistChange::RemoveTracks(removal), proxy).await?;
         println!("{}", "删除成功".green());
     } else {
         println!("{}", "取消删除操作".yellow());
@@ -581,75 +1209,347 @@ async fn perform_deletion(
     Ok(())
 }
 
-async fn handle_find_command(find_cmd: FindCommand) -> StdResult<()> {
-    let playlist = get_playlist().await;
-    if let Some(playlist) = playlist {
-        let mut results = playlist.tracks;
-        if let Some(bvid) = find_cmd.bvid {
-            results.retain(|track| track.bvid == bvid);
+async fn handle_find_command(find_cmd: FindCommand, proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
+    let mut results = list_tracks(proxy).await?;
+    if let Some(bvid) = find_cmd.bvid {
+        let bvid = normalize_bvid(&bvid)?;
+        results.retain(|track| track.bvid == bvid);
+    }
+    if let Some(cid) = find_cmd.cid {
+        results.retain(|track| track.cid == cid);
+    }
+    if let Some(title) = find_cmd.title {
+        results.retain(|track| track.title.contains(&title));
+    }
+    if let Some(owner) = find_cmd.owner {
+        results.retain(|track| track.owner.contains(&owner));
+    }
+    if let Some(tag) = find_cmd.tag {
+        results.retain(|track| track.has_tag(&tag));
+    }
+    if let Some(rating) = find_cmd.rating {
+        results.retain(|track| track.rating.is_some_and(|r| r >= rating));
+    }
+    if find_cmd.loved {
+        results.retain(|track| track.loved);
+    }
+    if results.is_empty() {
+        println!("没有找到符合条件的 track");
+    } else {
+        let list = results.iter().map(|t| t.to_println_string()).collect();
+        show_list_page(list).await;
+    }
+    Ok(())
+}
+
+async fn handle_search_command(
+    search_cmd: SearchCommand,
+    proxy: &MyPlayerProxy<'_>,
+) -> StdResult<()> {
+    let client = BiliClient::new()?;
+    let filter = SearchFilter {
+        order: search_cmd.order,
+        duration: search_cmd.duration,
+        tid: search_cmd.tid,
+    };
+    let mut page = 1;
+    loop {
+        let result = search_videos(&client, &search_cmd.keyword, &filter, page).await?;
+        if result.result.is_empty() {
+            println!("{}", "没有找到相关的视频".red());
+            return Ok(());
         }
-        if let Some(title) = find_cmd.title {
-            results.retain(|track| track.title.contains(&title));
+        for (i, video) in result.result.iter().enumerate() {
+            println!("{:<2}. {}", i + 1, video.to_println_string());
+        }
+        print!(
+            "{}",
+            format!(
+                "当前第 {} 页, 共 {} 页, 输入序号添加到歌曲列表 (如 1 3 5), 'p 序号' 立即播放, 'n' 下一页, 'b' 上一页, 'q' 退出：",
+                result.page.to_string().green(),
+                result.num_pages
+            )
+            .blue()
+        );
+        std::io::stdout().flush().unwrap();
+        let mut input = String::new();
+        let mut stdin = tokio::io::BufReader::new(tokio::io::stdin());
+        stdin
+            .read_line(&mut input)
+            .await
+            .expect("Failed to read line");
+        let input = input.trim();
+        let select = |s: &str| -> Option<Vec<&SearchVideo>> {
+            s.split_whitespace()
+                .map(|i| {
+                    i.parse::<usize>()
+                        .ok()
+                        .and_then(|i| result.result.get(i.checked_sub(1)?))
+                })
+                .collect()
+        };
+        match input {
+            "q" | "Q" => return Ok(()),
+            "n" | "N" if page < result.num_pages => page += 1,
+            "b" | "B" if page > 1 => page -= 1,
+            "n" | "N" | "b" | "B" => println!("{}", "没有更多的页面了".red()),
+            _ => {
+                let (play, indexes) = match input.strip_prefix('p') {
+                    Some(rest) => (true, rest),
+                    None => (false, input),
+                };
+                match select(indexes) {
+                    Some(videos) if !videos.is_empty() && (!play || videos.len() == 1) => {
+                        add_search_results(&client, &videos, play, proxy).await?;
+                    }
+                    _ => println!("{}", "无效的输入，请输入有效的序号".red()),
+                }
+            }
         }
-        if let Some(owner) = find_cmd.owner {
-            results.retain(|track| track.owner.contains(&owner));
+        println!("\n");
+    }
+}
+
+// 将选中的搜索结果导入歌曲列表, play 为 true 时导入后立即播放
+async fn add_search_results(
+    client: &BiliClient,
+    videos: &[&SearchVideo],
+    play: bool,
+    proxy: &MyPlayerProxy<'_>,
+) -> StdResult<()> {
+    for video in videos {
+        let target = ImportTarget::Video {
+            bvid: video.bvid.clone(),
+            page: None,
+        };
+        match import_target(client, target, proxy).await {
+            Ok(_) => println!("{}", format!("已添加：{}", video.title).green()),
+            Err(e) => println!("{}", format!("添加 {} 失败：{e}", video.title).red()),
         }
-        if results.is_empty() {
-            println!("没有找到符合条件的 track");
-        } else {
-            let list = results.iter().map(|t| t.to_println_string()).collect();
-            show_list_page(list).await;
+    }
+    if play {
+        let bvid = &videos[0].bvid;
+        if !is_rosesong_running(proxy).await? {
+            println!("{}", "rosesong 没有处于运行状态, 已添加到歌曲列表".yellow());
+        } else if let Some(id) = get_playlist().await?.resolve_track_id(bvid, None) {
+            proxy.play_track(&id).await?;
+            println!("播放 {}", videos[0].title);
         }
-    } else {
-        println!("{}", "歌曲列表文件不存在".red());
     }
     Ok(())
 }
 
-async fn display_playlist(list_cmd: ListCommand) -> StdResult<()> {
+async fn display_playlist(list_cmd: ListCommand, proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
     let is_empty = is_playlist_empty().await?;
     if is_empty {
         println!("{}", "歌曲列表为空".red());
         return Ok(());
     }
-    if let Some(playlist) = get_playlist().await {
-        if list_cmd.season {
-            let seasons = playlist.seasons;
-            let list = seasons.iter().map(|s| s.to_println_string()).collect();
-            show_list_page(list).await;
-        } else {
-            let tracks = playlist.tracks;
-            let list = tracks.iter().map(|t| t.to_println_string()).collect();
-            show_list_page(list).await;
-        }
+    let playlist = get_playlist().await?;
+    if list_cmd.season {
+        let seasons = playlist.seasons;
+        let list = seasons.iter().map(|s| s.to_println_string()).collect();
+        show_list_page(list).await;
+    } else if list_cmd.sources {
+        let sources = playlist.sources;
+        let list = sources.iter().map(|s| s.to_println_string()).collect();
+        show_list_page(list).await;
+    } else {
+        let tracks = list_tracks(proxy).await?;
+        let list = tracks.iter().map(|t| t.to_println_string()).collect();
+        show_list_page(list).await;
     }
     Ok(())
 }
 
-async fn update_season(proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
-    if let Some(mut playlist) = get_playlist().await {
-        // clean all season songs
-        let retain_tracks = playlist
-            .tracks
-            .clone()
-            .into_iter()
-            .filter(|t| t.sid.is_none())
-            .collect::<Vec<Track>>();
-        playlist.tracks = retain_tracks;
-        save_playlist_to_file(&playlist).await?;
-        // starting update season songs
-        for season in playlist.seasons {
-            println!("更新合集：{}", season.title.blue());
-            if let Err(e) = import_favorite_or_bvid_or_sid(None, None, Some(season.id)).await {
+/// 一个导入来源与上次同步时相比的变化
+struct SourceDiff {
+    added: Vec<RemoteEntry>,
+    removed: Vec<String>,
+    renamed: Vec<(TrackId, String, String)>,
+}
+
+impl SourceDiff {
+    fn new(source: &Source, entries: Vec<RemoteEntry>, tracks: &[Track]) -> Self {
+        let previous: HashSet<&str> = source.bvids.iter().map(String::as_str).collect();
+        let remote: HashSet<&str> = entries.iter().map(|e| e.bvid.as_str()).collect();
+        let removed = source
+            .bvids
+            .iter()
+            .filter(|bvid| !remote.contains(bvid.as_str()))
+            .cloned()
+            .collect();
+        let mut added = Vec::new();
+        let mut renamed = Vec::new();
+        for entry in entries {
+            if !previous.contains(entry.bvid.as_str()) {
+                added.push(entry);
+                continue;
+            }
+            // 多 P 视频的歌曲标题是分 P 标题, 无法与视频标题比较
+            let same_track = |t: &&Track| match &entry.track {
+                Some(track) => t.has_id(&track.id()),
+                None => t.bvid == entry.bvid && t.page.is_none(),
+            };
+            for track in tracks.iter().filter(same_track) {
+                if track.title != entry.title {
+                    renamed.push((track.id(), track.title.clone(), entry.title.clone()));
+                }
+            }
+        }
+        SourceDiff {
+            added,
+            removed,
+            renamed,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty()
+    }
+
+    fn print(&self, tracks: &[Track]) {
+        for entry in &self.added {
+            println!("  {} {} ({})", "+".green(), entry.title, entry.bvid);
+        }
+        for bvid in &self.removed {
+            let title = tracks
+                .iter()
+                .find(|t| &t.bvid == bvid)
+                .map_or("", |t| t.title.as_str());
+            println!("  {} {} ({})", "-".red(), title, bvid);
+        }
+        for (id, old, new) in &self.renamed {
+            println!("  {} {} -> {} ({})", "~".yellow(), old, new, id.bvid);
+        }
+    }
+}
+
+async fn sync_sources(sync_cmd: SyncCommand, proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
+    let mut playlist = get_playlist().await?;
+    if playlist.sources.is_empty() {
+        println!("没有需要同步的导入来源");
+        return Ok(());
+    }
+
+    let client = BiliClient::new()?;
+    let now = chrono::Local::now().timestamp();
+    let mut sources = std::mem::take(&mut playlist.sources);
+    // 所有来源中现有的视频, 从一个来源中移除但仍在其他来源中的视频不会被删除
+    let mut remote_bvids: HashSet<String> = HashSet::new();
+    let mut removed_bvids: HashSet<String> = HashSet::new();
+    let (mut added, mut removed, mut renamed) = (0, 0, 0);
+    let mut failures = Vec::new();
+    // 同步结果最后一起交给 rosesong 修改歌曲列表
+    let mut changes = Vec::new();
+    let mut renamed_patches = Vec::new();
+    for source in &mut sources {
+        println!("同步{}：{}", source.kind, source.title.blue());
+        let fetched = match fetch_source(&client, source).await {
+            Ok(fetched) => fetched,
+            Err(e) => {
                 eprintln!(
                     "{}",
-                    format!("更新合集[{}]失败：{}", season.title.blue(), e).red()
+                    format!("同步{}[{}]失败：{}", source.kind, source.title.blue(), e).red()
                 );
+                remote_bvids.extend(source.bvids.iter().cloned());
+                continue;
+            }
+        };
+        let bvids: Vec<String> = fetched.entries.iter().map(|e| e.bvid.clone()).collect();
+        remote_bvids.extend(bvids.iter().cloned());
+        let diff = SourceDiff::new(source, fetched.entries, &playlist.tracks);
+        if diff.is_empty() {
+            println!("  没有变化");
+        } else {
+            diff.print(&playlist.tracks);
+        }
+        added += diff.added.len();
+        removed += diff.removed.len();
+        renamed += diff.renamed.len();
+        if sync_cmd.dry_run {
+            continue;
+        }
+
+        for (id, _, title) in diff.renamed {
+            if let Some(track) = playlist.tracks.iter_mut().find(|t| t.has_id(&id)) {
+                track.title.clone_from(&title);
+                renamed_patches.push(TrackPatch {
+                    id,
+                    fields: vec![TrackField::Title(title)],
+                });
             }
         }
-        reload_playlist(proxy).await?;
-        println!("{}", "合集更新成功".green());
+        removed_bvids.extend(diff.removed);
+        let mut source_bvids = bvids;
+        let mut new_tracks = Vec::new();
+        if !diff.added.is_empty() {
+            let series_id = (source.kind == SourceKind::Series).then_some(source.id.as_str());
+            let failed_before = failures.len();
+            new_tracks = resolve_entries(&client, diff.added, series_id, &mut failures).await;
+            playlist.import_source(SourceImport {
+                tracks: new_tracks.clone(),
+                ..SourceImport::default()
+            });
+            // 获取失败的视频不记入来源, 下次同步时重试
+            let failed: HashSet<&str> = failures[failed_before..]
+                .iter()
+                .map(|f| f.bvid.as_str())
+                .collect();
+            source_bvids.retain(|b| !failed.contains(b.as_str()));
+        }
+        // 只更新已有合集的信息, 不带歌曲导入合集时不会替换合集中的歌曲
+        let season = fetched.season.and_then(|season| {
+            let mut old = playlist.find_season(season.kind, &season.id)?.clone();
+            old.title = season.title;
+            old.cover = season.cover;
+            old.intro = season.intro;
+            Some(old)
+        });
+        if season.is_some() {
+            changes.push(PlaylistChange::ImportSource(Box::new(SourceImport {
+                season,
+                ..SourceImport::default()
+            })));
+        }
+        if !fetched.title.is_empty() {
+            source.title = fetched.title;
+        }
+        source.set_bvids(source_bvids.iter().map(String::as_str));
+        source.synced_at = Some(now);
+        changes.push(PlaylistChange::ImportSource(Box::new(SourceImport {
+            tracks: new_tracks,
+            season: None,
+            source: Some(source.clone()),
+        })));
     }
+
+    let summary = format!("新增 {added} 个, 移除 {removed} 个, 标题变化 {renamed} 个");
+    if sync_cmd.dry_run {
+        println!("{}", format!("{summary} (未修改歌曲列表)").blue());
+        return Ok(());
+    }
+    if !renamed_patches.is_empty() {
+        changes.push(PlaylistChange::UpdateTracks(renamed_patches));
+    }
+    let removed_ids: Vec<TrackId> = playlist
+        .tracks
+        .iter()
+        .filter(|t| removed_bvids.contains(&t.bvid) && !remote_bvids.contains(&t.bvid))
+        .map(Track::id)
+        .collect();
+    if !removed_ids.is_empty() {
+        changes.push(PlaylistChange::RemoveTracks(TrackRemoval {
+            ids: removed_ids,
+            sources: Vec::new(),
+        }));
+    }
+    // 所有来源的同步结果一次性修改, 中途失败时歌曲列表保持不变
+    if !changes.is_empty() {
+        change_playlist(PlaylistChange::Batch(changes), proxy).await?;
+    }
+    print_failures(&failures);
+    println!("{}", format!("同步完成: {summary}").green());
     Ok(())
 }
 
@@ -697,17 +1597,21 @@ async fn show_list_page(list: Vec<String>) {
     }
 }
 
-async fn display_status(proxy: &MyPlayerProxy<'_>) -> Result<(), AppError> {
+async fn display_status(
+    status_cmd: StatusCommand,
+    proxy: &MyPlayerProxy<'_>,
+) -> Result<(), AppError> {
     // play list
-    let playlist = get_playlist().await.unwrap_or_default();
+    let playlist = get_playlist().await?;
     let is_playlist_empty = is_playlist_empty().await?;
     // current play info
-    let current_play_info = get_current_play_info().await.unwrap_or_default();
-    let mut current_play_season = None;
-    if let Some(sid) = current_play_info.playing_sid.clone() {
-        let season = playlist.seasons.iter().find(|s| s.id == sid).cloned();
-        current_play_season = season;
+    let current_play_info = get_current_play_info().await?;
+    let current_play_season = match current_play_info.selection() {
+        TrackSelection::Season(sid) => playlist.find_season(CollectionKind::Season, &sid),
+        TrackSelection::Series(id) => playlist.find_season(CollectionKind::Series, &id),
+        _ => None,
     }
+    .cloned();
 
     // show info
     println!("{}", "[rosesong 信息]".blue().bold());
@@ -732,7 +1636,11 @@ async fn display_status(proxy: &MyPlayerProxy<'_>) -> Result<(), AppError> {
     let play_status = {
         if let Some(season) = &current_play_season {
             let season_name = season.title.clone().yellow();
-            format!("仅播放合集 [{season_name}]").cyan()
+            format!("仅播放{} [{season_name}]", season.kind).cyan()
+        } else if let Some(tag) = &current_play_info.playing_tag {
+            format!("仅播放标签 [{}]", tag.yellow()).cyan()
+        } else if let Some(name) = &current_play_info.playing_smart {
+            format!("仅播放智能列表 [{}]", name.yellow()).cyan()
         } else {
             "全部歌曲".cyan()
         }
@@ -742,7 +1650,12 @@ async fn display_status(proxy: &MyPlayerProxy<'_>) -> Result<(), AppError> {
     let playlist_status = if is_playlist_empty {
         "空".red()
     } else {
-        format!("共 {} 首", playlist.tracks.len().to_string().cyan()).normal()
+        format!(
+            "共 {} 首, {}",
+            playlist.tracks.len().to_string().cyan(),
+            format_total_duration(&playlist.tracks).cyan()
+        )
+        .normal()
     };
     println!("全部歌曲: {playlist_status}\n");
 
@@ -751,8 +1664,9 @@ async fn display_status(proxy: &MyPlayerProxy<'_>) -> Result<(), AppError> {
             "空".red()
         } else {
             format!(
-                "共 {} 首歌曲",
-                current_play_info.current_tracks.len().to_string().yellow()
+                "共 {} 首歌曲, {}",
+                current_play_info.current_tracks.len().to_string().yellow(),
+                format_total_duration(&current_play_info.current_tracks).yellow()
             )
             .normal()
         };
@@ -773,6 +1687,474 @@ async fn display_status(proxy: &MyPlayerProxy<'_>) -> Result<(), AppError> {
         println!("BV号：{}", track.bvid.to_string().yellow());
         println!("标题：{}", track.title.yellow());
         println!("up主：{}", track.owner.yellow());
+        // 旧版本导入的歌曲缺少这些信息, 从歌曲列表中读取最新的记录
+        let info = playlist.find_track(&track.id()).unwrap_or(&track);
+        if let Some(duration) = info.duration {
+            println!("时长：{}", format_duration(duration).yellow());
+        }
+        if let Some(tname) = &info.tname {
+            println!("分区：{}", tname.yellow());
+        }
+        if let Some(pubdate) = info.pubdate_local() {
+            println!(
+                "发布时间：{}",
+                pubdate.format("%Y-%m-%d").to_string().yellow()
+            );
+        }
+        if let Some(views) = info.views {
+            println!("播放量：{}", views.to_string().yellow());
+        }
+        if let Some(desc) = info.desc.as_ref().filter(|d| !d.trim().is_empty()) {
+            println!("简介：{}", desc.trim().yellow());
+        }
+        if status_cmd.art {
+            display_cover(&playlist, &track).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 歌曲的总时长, 有歌曲缺少时长信息时一并提示
+fn format_total_duration(tracks: &[Track]) -> String {
+    let total: u64 = tracks.iter().filter_map(|t| t.duration).sum();
+    let unknown = tracks.iter().filter(|t| t.duration.is_none()).count();
+    if unknown == 0 {
+        format_duration(total)
+    } else {
+        format!("{} ({unknown} 首时长未知)", format_duration(total))
+    }
+}
+
+// 显示歌曲的封面, 歌曲没有封面时使用所属合集的封面
+async fn display_cover(playlist: &Playlist, track: &Track) -> StdResult<()> {
+    let client = BiliClient::new()?;
+    match cache_cover(client.http(), track, playlist.cover_of(track)).await {
+        Ok(Some(path)) => art::print_image(&path)?,
+        Ok(None) => println!("{}", "该歌曲没有封面，重新导入后可以获取封面".yellow()),
+        Err(e) => eprintln!("{}", format!("获取封面失败：{e}").red()),
+    }
+    Ok(())
+}
+
+/// 修改指定 bvid (默认为当前播放的歌曲) 的歌曲, 指定 cid 时只修改该分 P, 返回修改的歌曲数量
+async fn update_tracks_meta(
+    bvid: Option<String>,
+    cid: Option<String>,
+    proxy: &MyPlayerProxy<'_>,
+    fields: Vec<TrackField>,
+) -> StdResult<usize> {
+    let playlist = get_playlist().await?;
+    let ids: HashSet<TrackId> = match bvid.as_deref().map(normalize_bvid).transpose()? {
+        Some(bvid) => playlist
+            .tracks
+            .iter()
+            .filter(|t| t.bvid == bvid && cid.as_ref().is_none_or(|cid| &t.cid == cid))
+            .map(Track::id)
+            .collect(),
+        None => match get_current_play_info().await?.track {
+            Some(track) => HashSet::from([track.id()]),
+            None => {
+                println!("{}", "当前没有正在播放的歌曲, 请使用 -b 指定 bvid".red());
+                return Ok(0);
+            }
+        },
+    };
+    let patches: Vec<TrackPatch> = playlist
+        .tracks
+        .iter()
+        .filter(|t| ids.contains(&t.id()))
+        .map(|t| TrackPatch {
+            id: t.id(),
+            fields: fields.clone(),
+        })
+        .collect();
+    if patches.is_empty() {
+        println!("{}", "歌曲列表中没有找到该歌曲".red());
+        return Ok(0);
+    }
+    let count = patches.len();
+    change_playlist(PlaylistChange::UpdateTracks(patches), proxy).await?;
+    Ok(count)
+}
+
+async fn handle_rate_command(rate_cmd: RateCommand, proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
+    if rate_cmd.rating > 5 {
+        println!("{}", "评分范围为 1~5".red());
+        return Ok(());
+    }
+    let rating = (rate_cmd.rating > 0).then_some(rate_cmd.rating);
+    if update_tracks_meta(
+        rate_cmd.bvid,
+        rate_cmd.cid,
+        proxy,
+        vec![TrackField::Rating(rating)],
+    )
+    .await?
+        > 0
+    {
+        match rating {
+            Some(rating) => println!("{}", format!("评分已设置为 {rating}").green()),
+            None => println!("{}", "评分已清除".green()),
+        }
+    }
+    Ok(())
+}
+
+async fn handle_love_command(love_cmd: LoveCommand, proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
+    let loved = !love_cmd.undo;
+    if update_tracks_meta(
+        love_cmd.bvid,
+        love_cmd.cid,
+        proxy,
+        vec![TrackField::Loved(loved)],
+    )
+    .await?
+        > 0
+    {
+        if loved {
+            println!("{}", "已标记为喜欢".green());
+        } else {
+            println!("{}", "已取消喜欢".green());
+        }
+    }
+    Ok(())
+}
+
+async fn handle_tag_command(tag_cmd: TagCommand, proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
+    match tag_cmd.action {
+        TagAction::Add { tags, bvid, cid } => {
+            let count =
+                update_tracks_meta(bvid, cid, proxy, vec![TrackField::AddTags(tags)]).await?;
+            if count > 0 {
+                println!("{}", "添加标签成功".green());
+            }
+        }
+        TagAction::Rm { tags, bvid, cid } => {
+            let count =
+                update_tracks_meta(bvid, cid, proxy, vec![TrackField::RemoveTags(tags)]).await?;
+            if count > 0 {
+                println!("{}", "删除标签成功".green());
+            }
+        }
+        TagAction::Ls => {
+            let playlist = get_playlist().await?;
+            let mut tags: Vec<(String, usize)> = Vec::new();
+            for tag in playlist.tracks.iter().flat_map(|t| &t.tags) {
+                match tags.iter_mut().find(|(t, _)| t == tag) {
+                    Some((_, count)) => *count += 1,
+                    None => tags.push((tag.clone(), 1)),
+                }
+            }
+            if tags.is_empty() {
+                println!("{}", "还没有任何标签".yellow());
+            }
+            for (tag, count) in tags {
+                println!("{} [共{}首]", tag.green(), count);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 读取歌曲列表并计算智能列表中的歌曲
+async fn get_playlist_with_smart() -> StdResult<Playlist> {
+    let mut playlist = get_playlist().await?;
+    let counts = play_counts(&get_play_records().await?);
+    playlist.refresh_smart_playlists(get_smart_playlists().await?, &counts);
+    Ok(playlist)
+}
+
+async fn display_smart_playlists(smart_cmd: SmartCommand) -> StdResult<()> {
+    let playlist = get_playlist_with_smart().await?;
+    if let Some(name) = smart_cmd.name {
+        let tracks = playlist.find_tracks_in_smart(&name);
+        if tracks.is_empty() {
+            println!("{}", format!("智能列表 [{name}] 不存在或没有歌曲").red());
+        } else {
+            let list = tracks.iter().map(Track::to_println_string).collect();
+            show_list_page(list).await;
+        }
+    } else if playlist.smart_playlists.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "还没有定义智能列表, 请编辑 {}",
+                smart_playlist_file()?.display()
+            )
+            .yellow()
+        );
+    } else {
+        for smart in &playlist.smart_playlists {
+            println!("{} [共{}首]", smart.name.green(), smart.tracks.len());
+        }
+    }
+    Ok(())
+}
+
+async fn display_history(history_cmd: HistoryCommand) -> StdResult<()> {
+    let records = get_play_records().await?;
+    let list = records
+        .iter()
+        .rev()
+        .filter(|r| r.event == PlayEvent::Finish)
+        .take(history_cmd.number)
+        .map(|r| {
+            let skipped = if r.skipped {
+                " [跳过]".red()
+            } else {
+                "".normal()
+            };
+            format!(
+                "{} {} - {} ({}){}",
+                r.started_at_local()
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+                    .black(),
+                r.title.cyan(),
+                r.owner,
+                format_duration(r.listened_secs).yellow(),
+                skipped
+            )
+        })
+        .collect::<Vec<String>>();
+    if list.is_empty() {
+        println!("{}", "暂无播放记录".yellow());
+    } else {
+        show_list_page(list).await;
+    }
+    Ok(())
+}
+
+async fn handle_doctor_command(
+    doctor_cmd: DoctorCommand,
+    proxy: &MyPlayerProxy<'_>,
+) -> StdResult<()> {
+    let mut playlist = get_playlist().await?;
+    let original = playlist.tracks.clone();
+    let mut prune = false;
+    if doctor_cmd.check {
+        let mut bvids: Vec<String> = playlist.tracks.iter().map(|t| t.bvid.clone()).collect();
+        bvids.sort();
+        bvids.dedup();
+        println!("正在检查 {} 个视频", bvids.len());
+        let client = BiliClient::new()?;
+        for (bvid, reason) in check_availability(&client, &bvids).await {
+            match reason {
+                Some(reason) => playlist.mark_unavailable(&bvid, reason),
+                None => playlist.clear_unavailable(&bvid),
+            };
+        }
+    }
+
+    let unavailable: Vec<Track> = playlist.unavailable_tracks().into_iter().cloned().collect();
+    if unavailable.is_empty() {
+        println!("{}", "没有已失效的歌曲".green());
+    } else {
+        println!("共 {} 首歌曲已失效:", unavailable.len().to_string().red());
+        for track in &unavailable {
+            let marked_at = track
+                .unavailable
+                .as_ref()
+                .map(|u| u.marked_at_local().format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            println!(
+                "  {} {}",
+                track.to_println_string(),
+                format!("(发现于 {marked_at})").black()
+            );
+        }
+        if doctor_cmd.prune_unavailable
+            && (doctor_cmd.yes || confirm("是否从歌曲列表中删除这些歌曲? [y/n]: ").await)
+        {
+            prune = true;
+        } else if !doctor_cmd.prune_unavailable {
+            println!("使用 rsg doctor --prune-unavailable 删除这些歌曲");
+        }
+    }
+
+    // 只发送检查结果发生变化的失效标记
+    let updated: Vec<TrackPatch> = playlist
+        .tracks
+        .iter()
+        .zip(&original)
+        .filter(|(track, old)| track.unavailable != old.unavailable)
+        .map(|(track, _)| TrackPatch {
+            id: track.id(),
+            fields: vec![TrackField::Unavailable(track.unavailable.clone())],
+        })
+        .collect();
+    if !updated.is_empty() {
+        change_playlist(PlaylistChange::UpdateTracks(updated), proxy).await?;
+    }
+    if prune {
+        let removal = TrackRemoval {
+            ids: unavailable.iter().map(Track::id).collect(),
+            sources: Vec::new(),
+        };
+        change_playlist(PlaylistChange::RemoveTracks(removal), proxy).await?;
+        println!("{}", format!("已删除 {} 首歌曲", unavailable.len()).green());
+    }
+    Ok(())
+}
+
+async fn handle_lyrics_command(
+    lyrics_cmd: LyricsCommand,
+    proxy: &MyPlayerProxy<'_>,
+) -> StdResult<()> {
+    let client = BiliClient::new()?;
+    if lyrics_cmd.follow {
+        return follow_lyrics(&client, proxy).await;
+    }
+    let track = match lyrics_cmd.bvid {
+        Some(bvid) => {
+            let bvid = normalize_bvid(&bvid)?;
+            let playlist = get_playlist().await?;
+            playlist.tracks.into_iter().find(|t| t.bvid == bvid)
+        }
+        None => get_current_play_info().await?.get_current_track(),
+    };
+    let Some(track) = track else {
+        println!("{}", "没有找到对应的歌曲".red());
+        return Ok(());
+    };
+    let Some(lyrics) = load_lyrics(&client, &track).await? else {
+        println!("{}", format!("{} 没有 CC 字幕", track.title).yellow());
+        return Ok(());
+    };
+    if let Some(path) = lyrics_cmd.export {
+        fs::write(&path, lyrics.to_lrc()).await?;
+        println!("{}", format!("歌词已导出到 {path}").green());
+    } else {
+        println!("{}", track.title.blue().bold());
+        for line in &lyrics.lines {
+            println!("{}", line.text);
+        }
+    }
+    Ok(())
+}
+
+// 按 rosesong 的播放进度滚动显示歌词, 当前行高亮, 按 Ctrl-C 退出
+async fn follow_lyrics(client: &BiliClient, proxy: &MyPlayerProxy<'_>) -> StdResult<()> {
+    if !is_rosesong_running(proxy).await? {
+        println!("{}", "rosesong 没有处于运行状态".red());
+        return Ok(());
+    }
+    let mut current: Option<(TrackId, String, Option<Lyrics>)> = None;
+    let mut last_index = None;
+    loop {
+        let Ok((id, position)) = proxy.position().await else {
+            println!("{}", "当前没有播放的歌曲".red());
+            return Ok(());
+        };
+        if current.as_ref().is_none_or(|(cur, _, _)| *cur != id) {
+            let playlist = get_playlist().await?;
+            let Some(track) = playlist.find_track(&id) else {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                continue;
+            };
+            let lyrics = load_lyrics(client, track).await.unwrap_or_else(|e| {
+                eprintln!("{}", format!("获取歌词失败：{e}").red());
+                None
+            });
+            current = Some((id, track.title.clone(), lyrics));
+            last_index = Some(None);
+        }
+        if let Some((_, title, lyrics)) = &current {
+            let index = lyrics.as_ref().and_then(|l| l.line_index_at(position));
+            if last_index != Some(index) {
+                last_index = Some(index);
+                // 清屏后重新输出当前行附近的歌词
+                print!("\x1B[2J\x1B[H");
+                println!("{}\n", title.blue().bold());
+                match lyrics {
+                    Some(lyrics) => {
+                        let center = index.unwrap_or_default();
+                        let start = center.saturating_sub(3);
+                        let end = (center + 6).min(lyrics.lines.len());
+                        for (i, line) in lyrics.lines[start..end].iter().enumerate() {
+                            if Some(start + i) == index {
+                                println!("{}", line.text.green().bold());
+                            } else {
+                                println!("{}", line.text.dimmed());
+                            }
+                        }
+                    }
+                    None => println!("{}", "该歌曲没有 CC 字幕".yellow()),
+                }
+                std::io::stdout().flush().unwrap();
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+async fn display_stats(stats_cmd: StatsCommand) -> StdResult<()> {
+    let records = get_play_records().await?;
+    if stats_cmd.unplayed {
+        let counts = play_counts(&records);
+        let playlist = get_playlist().await?;
+        let list = playlist
+            .tracks
+            .iter()
+            .filter(|t| !counts.contains_key(&t.id()))
+            .map(Track::to_println_string)
+            .collect::<Vec<String>>();
+        if list.is_empty() {
+            println!("{}", "所有歌曲都至少完整播放过一次".green());
+        } else {
+            println!(
+                "从未完整播放过的歌曲: 共 {} 首",
+                list.len().to_string().cyan()
+            );
+            show_list_page(list).await;
+        }
+        return Ok(());
+    }
+
+    let stats = PlayStats::from_records(&records);
+    if stats.total_plays == 0 {
+        println!("{}", "暂无播放记录".yellow());
+        return Ok(());
+    }
+    println!("{}", "[收听统计]".blue().bold());
+    println!("播放次数：{}", stats.total_plays.to_string().cyan());
+    println!("收听时长：{}", format_duration(stats.total_secs).cyan());
+    println!(
+        "跳过率：{}\n",
+        format!("{:.1}%", stats.skip_rate() * 100.0).cyan()
+    );
+
+    println!("{}", "[最常播放的歌曲]".blue().bold());
+    for (i, t) in stats.top_tracks.iter().take(stats_cmd.top).enumerate() {
+        println!(
+            "{:<2}. {} - {} {} 次, {}",
+            i + 1,
+            t.title.cyan(),
+            t.owner,
+            t.plays.to_string().yellow(),
+            format_duration(t.listened_secs)
+        );
+    }
+
+    println!("\n{}", "[最常收听的 up 主]".blue().bold());
+    for (i, o) in stats.top_owners.iter().take(stats_cmd.top).enumerate() {
+        println!(
+            "{:<2}. {} {} 次, {}",
+            i + 1,
+            o.owner.cyan(),
+            o.plays.to_string().yellow(),
+            format_duration(o.listened_secs)
+        );
+    }
+
+    println!("\n{}", "[每日收听时长]".blue().bold());
+    for (date, secs) in stats.per_day.iter().take(stats_cmd.days) {
+        println!("{}  {}", date, format_duration(*secs).yellow());
+    }
+
+    println!("\n{}", "[每周收听时长]".blue().bold());
+    for (monday, secs) in stats.per_week.iter().take(stats_cmd.weeks) {
+        println!("{} 起  {}", monday, format_duration(*secs).yellow());
     }
     Ok(())
 }
@@ -788,26 +2170,69 @@ async fn handle_list_all(list_all_type: ListAllType) -> StdResult<()> {
     if is_empty {
         return Ok(());
     }
-    if let Some(playlist) = get_playlist().await {
-        match list_all_type {
-            ListAllType::Song => {
-                let tracks = playlist.tracks;
-                for track in tracks {
-                    println!("{},{} - {}", track.bvid, track.title, track.owner);
-                }
+    let playlist = get_playlist().await?;
+    match list_all_type {
+        ListAllType::Song => {
+            let tracks = playlist.tracks;
+            for track in tracks {
+                println!("{},{} - {}", track.bvid, track.title, track.owner);
             }
-            ListAllType::Season => {
-                let seasons = playlist.seasons;
-                for season in seasons {
-                    let total = playlist
-                        .tracks
-                        .iter()
-                        .filter(|t| t.sid == Some(season.id.clone()))
-                        .count();
-                    println!("{},{}  [共{}首]", season.id, season.title, total);
+        }
+        ListAllType::Season => {
+            for season in &playlist.seasons {
+                let total = playlist
+                    .find_tracks_in_season(season.kind, &season.id)
+                    .len();
+                println!("{},{}  [共{}首]", season.id, season.title, total);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_config_command(config_cmd: ConfigCommand) -> StdResult<()> {
+    match config_cmd.action {
+        ConfigAction::Get { key } => {
+            let config = load_config()?;
+            let keys = match key {
+                Some(key) => vec![key],
+                None => Config::keys(),
+            };
+            for key in keys {
+                match config.get(&key) {
+                    Some(value) => println!("{} = {}", key.cyan(), value),
+                    None => println!("{}", format!("没有名为 {key} 的配置项").red()),
                 }
             }
         }
+        ConfigAction::Set { key, value } => {
+            let config = load_config()?.set(&key, &value)?;
+            save_config(&config).await?;
+            println!("{}", format!("已将 {key} 设置为 {value}").green());
+        }
+        ConfigAction::Edit => {
+            let path = config_file()?;
+            if !path.exists() {
+                save_config(&Config::default()).await?;
+            }
+            let editor = std::env::var("VISUAL")
+                .or_else(|_| std::env::var("EDITOR"))
+                .unwrap_or_else(|_| "vi".to_string());
+            let status = Command::new(&editor).arg(&path).status().await?;
+            if !status.success() {
+                return Err(AppError::InvalidInput(format!(
+                    "编辑器 {editor} 异常退出: {status}"
+                )));
+            }
+            match load_config() {
+                Ok(_) => println!("{}", "配置已保存".green()),
+                Err(e) => println!(
+                    "{}",
+                    format!("配置文件有误, rosesong 会继续使用之前的配置: {e}").red()
+                ),
+            }
+        }
+        ConfigAction::Path => println!("{}", config_file()?.display()),
     }
     Ok(())
 }
@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+use crate::{
+    error::AppError,
+    utils::{config_file, write_atomic},
+};
+
+/// `config.toml` 中的所有配置项, 文件中缺少的配置使用默认值
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub network: NetworkConfig,
+    pub player: PlayerConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// HTTP 请求超时时间 (秒), 修改后需要重启 rosesong
+    pub timeout_secs: u64,
+    /// 获取音频地址失败时的最大尝试次数
+    pub fetch_retries: u32,
+    /// 播放失败时最多尝试播放的歌曲数量
+    pub play_retries: u32,
+    /// 音频流的读取超时时间 (秒)
+    pub stream_timeout_secs: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerConfig {
+    /// rsg vol up/down 每次调整的音量百分比
+    pub volume_step: u8,
+    /// 启动时音量淡入的时长 (秒), 为 0 时不淡入
+    pub fade_in_secs: u8,
+    /// 音频缓存队列的最大 buffer 数量
+    pub queue_max_buffers: u32,
+    /// 音频缓存队列的最大时长 (秒)
+    pub queue_max_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// 日志级别, 如 info、debug 或 `info, rosesong=debug`
+    pub level: String,
+    /// 单个日志文件的最大字节数, 修改后需要重启 rosesong
+    pub rotate_size: u64,
+    /// 保留的历史日志文件数量, 修改后需要重启 rosesong
+    pub keep_files: usize,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig {
+            timeout_secs: 5,
+            fetch_retries: 3,
+            play_retries: 5,
+            stream_timeout_secs: 5,
+        }
+    }
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        PlayerConfig {
+            volume_step: 5,
+            fade_in_secs: 3,
+            queue_max_buffers: 100,
+            queue_max_secs: 5,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: "info".to_string(),
+            rotate_size: 1_000_000,
+            keep_files: 3,
+        }
+    }
+}
+
+const LOG_LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+impl Config {
+    /// 检查配置项的取值范围
+    pub fn validate(&self) -> Result<(), AppError> {
+        let check = |ok: bool, message: &str| {
+            if ok {
+                Ok(())
+            } else {
+                Err(AppError::InvalidInput(message.to_string()))
+            }
+        };
+        check(
+            (1..=120).contains(&self.network.timeout_secs),
+            "network.timeout_secs 的取值范围为 1~120",
+        )?;
+        check(
+            (1..=10).contains(&self.network.fetch_retries),
+            "network.fetch_retries 的取值范围为 1~10",
+        )?;
+        check(
+            (1..=100).contains(&self.network.play_retries),
+            "network.play_retries 的取值范围为 1~100",
+        )?;
+        check(
+            (1..=120).contains(&self.network.stream_timeout_secs),
+            "network.stream_timeout_secs 的取值范围为 1~120",
+        )?;
+        check(
+            (1..=100).contains(&self.player.volume_step),
+            "player.volume_step 的取值范围为 1~100",
+        )?;
+        check(
+            self.player.fade_in_secs <= 30,
+            "player.fade_in_secs 不能超过 30",
+        )?;
+        check(
+            self.player.queue_max_buffers >= 1,
+            "player.queue_max_buffers 不能小于 1",
+        )?;
+        check(
+            (1..=60).contains(&self.player.queue_max_secs),
+            "player.queue_max_secs 的取值范围为 1~60",
+        )?;
+        // 每一项都是 `级别` 或 `模块=级别`
+        let valid_level = self.log.level.split(',').all(|spec| {
+            let level = spec.rsplit('=').next().unwrap_or_default().trim();
+            LOG_LEVELS.contains(&level.to_lowercase().as_str())
+        });
+        check(
+            valid_level,
+            "log.level 只能为 off、error、warn、info、debug 或 trace",
+        )?;
+        check(
+            self.log.rotate_size >= 10_000,
+            "log.rotate_size 不能小于 10000 字节",
+        )?;
+        check(self.log.keep_files >= 1, "log.keep_files 不能小于 1")
+    }
+
+    /// 所有配置项的名称, 格式为 `分组.名称`
+    pub fn keys() -> Vec<String> {
+        let Ok(Value::Table(table)) = Value::try_from(Config::default()) else {
+            return Vec::new();
+        };
+        table
+            .iter()
+            .flat_map(|(section, value)| {
+                value
+                    .as_table()
+                    .into_iter()
+                    .flat_map(move |t| t.keys().map(move |key| format!("{section}.{key}")))
+            })
+            .collect()
+    }
+
+    /// 按 `分组.名称` 读取配置项, 名称不存在时返回 None
+    pub fn get(&self, key: &str) -> Option<String> {
+        let (section, name) = key.split_once('.')?;
+        let value = Value::try_from(self).ok()?;
+        match value.get(section)?.get(name)? {
+            Value::String(s) => Some(s.clone()),
+            value => Some(value.to_string()),
+        }
+    }
+
+    /// 按 `分组.名称` 修改配置项, 返回修改并检查后的新配置
+    pub fn set(&self, key: &str, value: &str) -> Result<Config, AppError> {
+        let unknown_key = || AppError::InvalidInput(format!("没有名为 {key} 的配置项"));
+        let (section, name) = key.split_once('.').ok_or_else(unknown_key)?;
+        let mut table = Value::try_from(self)
+            .map_err(|_| AppError::DataParsing("无法将配置转换为 TOML".to_string()))?;
+        let entry = table
+            .get_mut(section)
+            .and_then(|s| s.get_mut(name))
+            .ok_or_else(unknown_key)?;
+        let invalid_value = || AppError::InvalidInput(format!("{key} 的值无效: {value}"));
+        *entry = match entry {
+            Value::Integer(_) => Value::Integer(value.parse().map_err(|_| invalid_value())?),
+            Value::Boolean(_) => Value::Boolean(value.parse().map_err(|_| invalid_value())?),
+            _ => Value::String(value.to_string()),
+        };
+        let config: Config = table.try_into().map_err(|_| invalid_value())?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// 读取配置文件, 文件不存在时使用默认配置, 文件内容有误时返回错误
+pub fn load_config() -> Result<Config, AppError> {
+    let path = config_file()?;
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let content = std::fs::read_to_string(path)?;
+    let config: Config = toml::from_str(&content)?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// 保存配置文件, 通过原子写入避免 rosesong 重新加载时读到写了一半的文件
+pub async fn save_config(config: &Config) -> Result<(), AppError> {
+    let content = toml::to_string(config)
+        .map_err(|_| AppError::DataParsing("无法将配置转换为 TOML".to_string()))?;
+    write_atomic(&config_file()?, content).await
+}
@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use reqwest::Client;
+
+use crate::{error::AppError, model::Track, utils::covers_dir};
+
+/// 缓存的封面缩略图的边长
+const THUMBNAIL_SIZE: u32 = 300;
+
+/// 歌曲封面的缓存文件, 同一视频的分 P 共用一张封面
+pub fn cover_file(track: &Track) -> Result<PathBuf, AppError> {
+    Ok(covers_dir()?.join(format!("{}.jpg", track.bvid)))
+}
+
+/// 下载歌曲的封面缩略图到缓存目录, 封面地址由 `Playlist::cover_of` 选出,
+/// 已缓存时直接返回路径, 没有封面时返回 None
+pub async fn cache_cover(
+    client: &Client,
+    track: &Track,
+    cover: &str,
+) -> Result<Option<PathBuf>, AppError> {
+    let path = cover_file(track)?;
+    if path.exists() {
+        return Ok(Some(path));
+    }
+    if cover.is_empty() {
+        return Ok(None);
+    }
+    // 通过图片地址后缀让 B 站的图床返回裁剪成正方形的缩略图
+    let url = format!(
+        "{}@{THUMBNAIL_SIZE}w_{THUMBNAIL_SIZE}h_1c.jpg",
+        cover.replacen("http://", "https://", 1)
+    );
+    let response = client.get(&url).send().await?.error_for_status()?;
+    let bytes = response.bytes().await?;
+    tokio::fs::create_dir_all(covers_dir()?).await?;
+    tokio::fs::write(&path, &bytes).await?;
+    Ok(Some(path))
+}
+
+/// 封面缓存文件的 file:// 地址, 用于 mpris:artUrl
+pub fn art_url(path: &std::path::Path) -> String {
+    format!("file://{}", path.display())
+}
@@ -20,7 +20,7 @@ pub enum AppError {
     JoinTask(#[from] JoinError),
     #[error("GStreamer initialization error: {0}")]
     Init(String),
-    #[error("TOML parsing error")]
+    #[error("TOML parsing error: {0}")]
     TomlParsing(#[from] TomlError),
     #[error("Fetch error: {0}")]
     Fetch(String),
@@ -52,6 +52,32 @@ pub enum AppError {
     OneshotRecv(#[from] tokio::sync::oneshot::error::RecvError),
     #[error("Zbus error")]
     Zbus(#[from] ZbusError),
+    #[error("Not logged in or session expired")]
+    NotLoggedIn,
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
+    #[error("Resource not found: {0}")]
+    NotFound(String),
+    #[error("Request blocked by risk control ({0})")]
+    RiskControl(i64),
+    #[error("Video {0} has been deleted")]
+    VideoDeleted(String),
+    #[error("Video is private or under review: {0}")]
+    VideoPrivate(String),
+    #[error("Video is not available in this region: {0}")]
+    RegionLocked(String),
+    #[error("{path} is corrupt, please fix or remove it: {message}")]
+    CorruptFile { path: String, message: String },
+    #[error("{path} has version {version}, which is newer than this rosesong supports")]
+    UnsupportedVersion { path: String, version: u32 },
+    #[error("Failed to migrate {path} from version {from}: {message}")]
+    Migration {
+        path: String,
+        from: u32,
+        message: String,
+    },
+    #[error("Bilibili API error {code}: {message}")]
+    Api { code: i64, message: String },
 }
 
 impl From<BoolError> for AppError {
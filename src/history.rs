@@ -0,0 +1,210 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    error::AppError,
+    model::{Track, TrackId},
+    utils::history_file,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayEvent {
+    Start,
+    Finish,
+}
+
+/// 播放历史中的一条记录, 每首歌曲开始和结束时各追加一条
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlayRecord {
+    pub event: PlayEvent,
+    pub bvid: String,
+    pub cid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series_id: Option<String>,
+    pub title: String,
+    pub owner: String,
+    /// 开始播放的时间 (unix 时间戳, 秒)
+    pub started_at: i64,
+    /// 实际收听的时长 (秒), 仅 `Finish` 记录有效
+    #[serde(default)]
+    pub listened_secs: u64,
+    /// 是否在播放完之前被切走, 仅 `Finish` 记录有效
+    #[serde(default)]
+    pub skipped: bool,
+}
+
+impl PlayRecord {
+    pub fn start(track: &Track) -> Self {
+        Self {
+            event: PlayEvent::Start,
+            bvid: track.bvid.clone(),
+            cid: track.cid.clone(),
+            sid: track.sid.clone(),
+            series_id: track.series_id.clone(),
+            title: track.title.clone(),
+            owner: track.owner.clone(),
+            started_at: Local::now().timestamp(),
+            listened_secs: 0,
+            skipped: false,
+        }
+    }
+
+    pub fn finish(&self, listened_secs: u64, skipped: bool) -> Self {
+        Self {
+            event: PlayEvent::Finish,
+            listened_secs,
+            skipped,
+            ..self.clone()
+        }
+    }
+
+    pub fn track_id(&self) -> TrackId {
+        TrackId {
+            bvid: self.bvid.clone(),
+            cid: self.cid.clone(),
+        }
+    }
+
+    pub fn started_at_local(&self) -> DateTime<Local> {
+        Local
+            .timestamp_opt(self.started_at, 0)
+            .single()
+            .unwrap_or_else(Local::now)
+    }
+}
+
+pub async fn append_play_record(record: &PlayRecord) -> Result<(), AppError> {
+    let mut line = serde_json::to_string(record)
+        .map_err(|_| AppError::DataParsing("Failed to serialize PlayRecord".to_string()))?;
+    line.push('\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_file()?)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// 读取全部播放历史, 无法解析的行会被忽略
+pub async fn get_play_records() -> Result<Vec<PlayRecord>, AppError> {
+    let file_path = history_file()?;
+    if !file_path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = tokio::fs::read_to_string(&file_path).await?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<PlayRecord>(line).ok())
+        .collect())
+}
+
+/// 每首歌曲完整播放的次数
+pub fn play_counts(records: &[PlayRecord]) -> HashMap<TrackId, u32> {
+    let mut counts = HashMap::new();
+    for record in records {
+        if record.event == PlayEvent::Finish && !record.skipped {
+            *counts.entry(record.track_id()).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+pub struct TrackStat {
+    pub id: TrackId,
+    pub title: String,
+    pub owner: String,
+    pub plays: u32,
+    pub listened_secs: u64,
+}
+
+pub struct OwnerStat {
+    pub owner: String,
+    pub plays: u32,
+    pub listened_secs: u64,
+}
+
+#[derive(Default)]
+pub struct PlayStats {
+    /// 按播放次数降序
+    pub top_tracks: Vec<TrackStat>,
+    /// 按播放次数降序
+    pub top_owners: Vec<OwnerStat>,
+    /// 每天的收听秒数, 按日期降序
+    pub per_day: Vec<(NaiveDate, u64)>,
+    /// 每周的收听秒数 (以该周周一表示), 按日期降序
+    pub per_week: Vec<(NaiveDate, u64)>,
+    pub total_plays: usize,
+    pub skipped_plays: usize,
+    pub total_secs: u64,
+}
+
+impl PlayStats {
+    pub fn from_records(records: &[PlayRecord]) -> Self {
+        let mut tracks: HashMap<TrackId, TrackStat> = HashMap::new();
+        let mut owners: HashMap<&str, OwnerStat> = HashMap::new();
+        let mut per_day: HashMap<NaiveDate, u64> = HashMap::new();
+        let mut per_week: HashMap<NaiveDate, u64> = HashMap::new();
+        let mut stats = PlayStats::default();
+
+        for record in records.iter().filter(|r| r.event == PlayEvent::Finish) {
+            stats.total_plays += 1;
+            stats.total_secs += record.listened_secs;
+            if record.skipped {
+                stats.skipped_plays += 1;
+            }
+            let track = tracks
+                .entry(record.track_id())
+                .or_insert_with(|| TrackStat {
+                    id: record.track_id(),
+                    title: record.title.clone(),
+                    owner: record.owner.clone(),
+                    plays: 0,
+                    listened_secs: 0,
+                });
+            track.plays += 1;
+            track.listened_secs += record.listened_secs;
+            let owner = owners.entry(&record.owner).or_insert_with(|| OwnerStat {
+                owner: record.owner.clone(),
+                plays: 0,
+                listened_secs: 0,
+            });
+            owner.plays += 1;
+            owner.listened_secs += record.listened_secs;
+
+            let date = record.started_at_local().date_naive();
+            *per_day.entry(date).or_default() += record.listened_secs;
+            let monday = date - Days::new(u64::from(date.weekday().num_days_from_monday()));
+            *per_week.entry(monday).or_default() += record.listened_secs;
+        }
+
+        stats.top_tracks = tracks.into_values().collect();
+        stats
+            .top_tracks
+            .sort_by_key(|t| Reverse((t.plays, t.listened_secs)));
+        stats.top_owners = owners.into_values().collect();
+        stats
+            .top_owners
+            .sort_by_key(|o| Reverse((o.plays, o.listened_secs)));
+        stats.per_day = per_day.into_iter().collect();
+        stats.per_day.sort_by_key(|d| Reverse(d.0));
+        stats.per_week = per_week.into_iter().collect();
+        stats.per_week.sort_by_key(|w| Reverse(w.0));
+        stats
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn skip_rate(&self) -> f64 {
+        if self.total_plays == 0 {
+            0.0
+        } else {
+            self.skipped_plays as f64 / self.total_plays as f64
+        }
+    }
+}
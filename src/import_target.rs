@@ -0,0 +1,326 @@
+use std::fmt::Display;
+
+use reqwest::{Client, Url};
+
+use crate::error::AppError;
+
+/// 从 B 站链接或 ID 中解析出的导入目标
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportTarget {
+    /// 视频, `page` 为链接中 `?p=N` 指定的分 P
+    Video { bvid: String, page: Option<u32> },
+    /// 收藏夹
+    Favorite { fid: String },
+    /// 合集
+    Season { sid: String },
+    /// 系列 (列表)
+    Series { sid: String },
+    /// UP 主的全部投稿, 可按标题关键词和发布日期 (YYYY-MM-DD) 过滤
+    Space {
+        mid: String,
+        keyword: Option<String>,
+        since: Option<String>,
+    },
+}
+
+impl Display for ImportTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportTarget::Video { bvid, page: None } => write!(f, "视频 {bvid}"),
+            ImportTarget::Video {
+                bvid,
+                page: Some(page),
+            } => write!(f, "视频 {bvid} P{page}"),
+            ImportTarget::Favorite { fid } => write!(f, "收藏夹 {fid}"),
+            ImportTarget::Season { sid } => write!(f, "合集 {sid}"),
+            ImportTarget::Series { sid } => write!(f, "系列 {sid}"),
+            ImportTarget::Space { mid, .. } => write!(f, "UP 主 {mid} 的投稿"),
+        }
+    }
+}
+
+const AV2BV_TABLE: &[u8] = b"FcwAPNKTMug3GV5Lj7EJnHpWsx4tb8haYeviqBz6rkCy12mUSDQX9RdoZf";
+const AV2BV_XOR: u64 = 23_442_827_791_579;
+const AV2BV_MAX_AID: u64 = 1 << 51;
+
+/// 将 av 号转换为 bvid
+pub fn av_to_bvid(aid: u64) -> String {
+    let mut bytes = *b"BV1000000000";
+    let mut tmp = (AV2BV_MAX_AID | aid) ^ AV2BV_XOR;
+    let mut index = bytes.len() - 1;
+    while tmp > 0 {
+        #[allow(clippy::cast_possible_truncation)]
+        let digit = (tmp % 58) as usize;
+        bytes[index] = AV2BV_TABLE[digit];
+        tmp /= 58;
+        index -= 1;
+    }
+    bytes.swap(3, 9);
+    bytes.swap(4, 7);
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+fn parse_bvid(s: &str) -> Option<String> {
+    let valid = s.len() == 12
+        && s.chars().all(|c| c.is_ascii_alphanumeric())
+        && s[..2].eq_ignore_ascii_case("bv");
+    valid.then(|| format!("BV{}", &s[2..]))
+}
+
+fn parse_aid(s: &str) -> Option<u64> {
+    let digits = s.strip_prefix("av").or_else(|| s.strip_prefix("AV"))?;
+    digits.parse::<u64>().ok()
+}
+
+/// 解析不带链接的 ID: `BV...` 或 `av...`
+fn parse_id(input: &str) -> Option<ImportTarget> {
+    let bvid = parse_bvid(input).or_else(|| parse_aid(input).map(av_to_bvid))?;
+    Some(ImportTarget::Video { bvid, page: None })
+}
+
+fn query_param(url: &Url, key: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// `ml123456` 形式的收藏夹 ID
+fn parse_ml(segment: &str) -> Option<String> {
+    segment
+        .strip_prefix("ml")
+        .filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+        .map(String::from)
+}
+
+fn parse_url(url: &Url) -> Option<ImportTarget> {
+    let host = url.host_str()?;
+    let segments = url
+        .path_segments()
+        .map(|s| s.filter(|s| !s.is_empty()).collect::<Vec<&str>>())
+        .unwrap_or_default();
+
+    // 视频: www.bilibili.com/video/BV...?p=N, m.bilibili.com/video/av...
+    if let Some(pos) = segments.iter().position(|s| *s == "video") {
+        if let Some(ImportTarget::Video { bvid, .. }) =
+            segments.get(pos + 1).and_then(|s| parse_id(s))
+        {
+            let page = query_param(url, "p").and_then(|p| p.parse::<u32>().ok());
+            return Some(ImportTarget::Video { bvid, page });
+        }
+    }
+
+    // 收藏夹: space.bilibili.com/<mid>/favlist?fid=..., medialist/detail/ml..., list/ml...
+    if let Some(fid) = query_param(url, "fid") {
+        return Some(ImportTarget::Favorite { fid });
+    }
+    if let Some(fid) = segments.iter().find_map(|s| parse_ml(s)) {
+        return Some(ImportTarget::Favorite { fid });
+    }
+
+    // 合集和系列: space.bilibili.com/<mid>/channel/collectiondetail?sid=...,
+    // space.bilibili.com/<mid>/channel/seriesdetail?sid=...,
+    // space.bilibili.com/<mid>/lists/<sid>?type=season|series,
+    // medialist/play/<mid>?business=space_collection|space_series&business_id=...
+    if host.starts_with("space.") && segments.contains(&"collectiondetail") {
+        return query_param(url, "sid").map(|sid| ImportTarget::Season { sid });
+    }
+    if host.starts_with("space.") && segments.contains(&"seriesdetail") {
+        return query_param(url, "sid").map(|sid| ImportTarget::Series { sid });
+    }
+    if host.starts_with("space.") && segments.get(1) == Some(&"lists") {
+        let sid = (*segments.get(2)?).to_string();
+        return match query_param(url, "type").as_deref() {
+            None | Some("season") => Some(ImportTarget::Season { sid }),
+            Some("series") => Some(ImportTarget::Series { sid }),
+            Some(_) => None,
+        };
+    }
+    match query_param(url, "business").as_deref() {
+        Some("space_collection") => {
+            return query_param(url, "business_id").map(|sid| ImportTarget::Season { sid });
+        }
+        Some("space_series") => {
+            return query_param(url, "business_id").map(|sid| ImportTarget::Series { sid });
+        }
+        _ => {}
+    }
+
+    // 其他带有 bvid 参数的链接, 例如播放列表页面
+    if let Some(target) = query_param(url, "bvid").and_then(|bvid| parse_id(&bvid)) {
+        return Some(target);
+    }
+
+    // UP 主空间: space.bilibili.com/<mid>, space.bilibili.com/<mid>/upload/video
+    if host.starts_with("space.") {
+        return segments
+            .first()
+            .filter(|mid| mid.chars().all(|c| c.is_ascii_digit()))
+            .map(|mid| ImportTarget::Space {
+                mid: (*mid).to_string(),
+                keyword: None,
+                since: None,
+            });
+    }
+    None
+}
+
+fn to_url(input: &str) -> Option<Url> {
+    if input.contains("://") {
+        Url::parse(input).ok()
+    } else if input.contains('/') || input.contains('.') {
+        Url::parse(&format!("https://{input}")).ok()
+    } else {
+        None
+    }
+}
+
+/// 解析 B 站链接或 ID, 不支持需要网络请求的 b23.tv 短链接
+pub fn parse_import_target(input: &str) -> Result<ImportTarget, AppError> {
+    let input = input.trim();
+    let invalid = || AppError::InvalidInput(format!("无法识别的链接或 ID: {input}"));
+    if let Some(target) = parse_id(input) {
+        return Ok(target);
+    }
+    let url = to_url(input).ok_or_else(invalid)?;
+    parse_url(&url).ok_or_else(invalid)
+}
+
+fn is_short_link(input: &str) -> bool {
+    to_url(input)
+        .and_then(|url| {
+            url.host_str()
+                .map(|h| h == "b23.tv" || h.ends_with(".b23.tv"))
+        })
+        .unwrap_or(false)
+}
+
+/// 解析 B 站链接或 ID, b23.tv 短链接会先通过重定向获取真实链接
+pub async fn resolve_import_target(client: &Client, input: &str) -> Result<ImportTarget, AppError> {
+    let input = input.trim();
+    if !is_short_link(input) {
+        return parse_import_target(input);
+    }
+    let url = to_url(input).ok_or_else(|| AppError::InvalidInput(input.to_string()))?;
+    let response = client.get(url).send().await?;
+    let resolved = response.url().to_string();
+    log::info!("resolved short link {} to {}", input, resolved);
+    parse_import_target(&resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(bvid: &str, page: Option<u32>) -> ImportTarget {
+        ImportTarget::Video {
+            bvid: bvid.to_string(),
+            page,
+        }
+    }
+
+    fn parse(input: &str) -> ImportTarget {
+        parse_import_target(input).unwrap()
+    }
+
+    #[test]
+    fn converts_av_to_bvid() {
+        // B 站公布的示例
+        assert_eq!(av_to_bvid(2), "BV1xx411c7mD");
+        assert_eq!(av_to_bvid(170_001), "BV17x411w7KC");
+    }
+
+    #[test]
+    fn parses_ids() {
+        assert_eq!(parse("BV17x411w7KC"), video("BV17x411w7KC", None));
+        assert_eq!(parse("bv17x411w7KC"), video("BV17x411w7KC", None));
+        assert_eq!(parse("av170001"), video("BV17x411w7KC", None));
+        assert_eq!(parse(" AV2 "), video("BV1xx411c7mD", None));
+    }
+
+    #[test]
+    fn parses_video_urls() {
+        assert_eq!(
+            parse("https://www.bilibili.com/video/BV17x411w7KC/?p=3&spm_id_from=333.788#reply123"),
+            video("BV17x411w7KC", Some(3))
+        );
+        assert_eq!(
+            parse("m.bilibili.com/video/av170001"),
+            video("BV17x411w7KC", None)
+        );
+    }
+
+    #[test]
+    fn parses_favorite_urls() {
+        let favorite = ImportTarget::Favorite {
+            fid: "123456".to_string(),
+        };
+        assert_eq!(
+            parse("https://space.bilibili.com/10086/favlist?fid=123456&ftype=create"),
+            favorite
+        );
+        assert_eq!(
+            parse("https://www.bilibili.com/medialist/detail/ml123456"),
+            favorite
+        );
+        assert_eq!(parse("https://www.bilibili.com/list/ml123456"), favorite);
+    }
+
+    #[test]
+    fn parses_season_and_series_urls() {
+        let season = ImportTarget::Season {
+            sid: "2001".to_string(),
+        };
+        let series = ImportTarget::Series {
+            sid: "3001".to_string(),
+        };
+        assert_eq!(
+            parse("https://space.bilibili.com/10086/channel/collectiondetail?sid=2001"),
+            season
+        );
+        assert_eq!(
+            parse("https://space.bilibili.com/10086/channel/seriesdetail?sid=3001"),
+            series
+        );
+        assert_eq!(parse("https://space.bilibili.com/10086/lists/2001"), season);
+        assert_eq!(
+            parse("https://space.bilibili.com/10086/lists/3001?type=series"),
+            series
+        );
+        assert_eq!(
+            parse("https://www.bilibili.com/medialist/play/10086?business=space_collection&business_id=2001"),
+            season
+        );
+    }
+
+    #[test]
+    fn parses_space_urls() {
+        let space = ImportTarget::Space {
+            mid: "10086".to_string(),
+            keyword: None,
+            since: None,
+        };
+        assert_eq!(parse("https://space.bilibili.com/10086"), space);
+        assert_eq!(
+            parse("https://space.bilibili.com/10086/upload/video"),
+            space
+        );
+    }
+
+    #[test]
+    fn detects_short_links() {
+        assert!(is_short_link("https://b23.tv/AbCdEf"));
+        assert!(is_short_link("b23.tv/AbCdEf"));
+        assert!(!is_short_link(
+            "https://www.bilibili.com/video/BV17x411w7KC"
+        ));
+        // 短链接需要联网解析, 不能直接识别
+        assert!(parse_import_target("https://b23.tv/AbCdEf").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_input() {
+        assert!(parse_import_target("hello").is_err());
+        assert!(parse_import_target("https://www.example.com/video/abc").is_err());
+    }
+}
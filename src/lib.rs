@@ -1,3 +1,10 @@
+pub mod bili_client;
+pub mod config;
+pub mod cover;
 pub mod error;
+pub mod history;
+pub mod import_target;
+pub mod lyrics;
 pub mod model;
+pub mod session;
 pub mod utils;
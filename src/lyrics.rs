@@ -0,0 +1,176 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::{bili_client::BiliClient, error::AppError, model::Track, utils::lyrics_dir};
+
+/// 一行带时间的歌词, 时间单位为秒
+#[derive(Clone, Debug)]
+pub struct LyricLine {
+    pub start: f64,
+    pub text: String,
+}
+
+/// 按时间排序的歌词, 来自视频的 CC 字幕
+#[derive(Clone, Debug, Default)]
+pub struct Lyrics {
+    pub lines: Vec<LyricLine>,
+}
+
+#[derive(Deserialize)]
+struct SubtitleLine {
+    from: f64,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct SubtitleBody {
+    body: Vec<SubtitleLine>,
+}
+
+#[derive(Deserialize)]
+struct SubtitleInfo {
+    lan: String,
+    #[serde(default)]
+    subtitle_url: String,
+}
+
+#[derive(Deserialize)]
+struct SubtitleList {
+    #[serde(default)]
+    subtitles: Vec<SubtitleInfo>,
+}
+
+#[derive(Deserialize)]
+struct PlayerInfo {
+    subtitle: Option<SubtitleList>,
+}
+
+impl Lyrics {
+    fn from_subtitle(subtitle: SubtitleBody) -> Self {
+        let mut lines: Vec<LyricLine> = subtitle
+            .body
+            .into_iter()
+            .map(|line| LyricLine {
+                start: line.from,
+                text: line.content.trim().to_string(),
+            })
+            .collect();
+        lines.sort_by(|a, b| a.start.total_cmp(&b.start));
+        Lyrics { lines }
+    }
+
+    /// 解析 `[mm:ss.xx]歌词` 格式的 LRC 文本, 忽略 `[ti:]` 等标签
+    pub fn from_lrc(content: &str) -> Self {
+        let mut lines = Vec::new();
+        for line in content.lines() {
+            let mut rest = line.trim();
+            let mut starts = Vec::new();
+            while let Some(tag) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+                match parse_timestamp(tag.0) {
+                    Some(start) => starts.push(start),
+                    None => break,
+                }
+                rest = tag.1;
+            }
+            for start in starts {
+                lines.push(LyricLine {
+                    start,
+                    text: rest.trim().to_string(),
+                });
+            }
+        }
+        lines.sort_by(|a, b| a.start.total_cmp(&b.start));
+        Lyrics { lines }
+    }
+
+    pub fn to_lrc(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| format!("[{}]{}\n", format_timestamp(line.start), line.text))
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// 播放到 position 秒时对应的歌词行, 第一行开始之前返回 None
+    pub fn line_index_at(&self, position: f64) -> Option<usize> {
+        self.lines
+            .partition_point(|line| line.start <= position)
+            .checked_sub(1)
+    }
+
+    pub fn line_at(&self, position: f64) -> Option<&LyricLine> {
+        self.line_index_at(position).map(|i| &self.lines[i])
+    }
+}
+
+fn parse_timestamp(tag: &str) -> Option<f64> {
+    let (min, sec) = tag.split_once(':')?;
+    let min: u32 = min.parse().ok()?;
+    let sec: f64 = sec.parse().ok()?;
+    Some(f64::from(min) * 60.0 + sec)
+}
+
+fn format_timestamp(secs: f64) -> String {
+    let centis = (secs * 100.0).round() as u64;
+    format!(
+        "{:02}:{:02}.{:02}",
+        centis / 6000,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+/// 歌曲对应的 LRC 缓存文件
+pub fn lyrics_file(track: &Track) -> Result<PathBuf, AppError> {
+    Ok(lyrics_dir()?.join(format!("{}_{}.lrc", track.bvid, track.cid)))
+}
+
+/// 获取视频的 CC 字幕作为歌词, 优先使用人工上传的中文字幕, 没有字幕时返回 None
+pub async fn fetch_lyrics(client: &BiliClient, track: &Track) -> Result<Option<Lyrics>, AppError> {
+    let url = "https://api.bilibili.com/x/player/wbi/v2";
+    let params = [("bvid", track.bvid.as_str()), ("cid", track.cid.as_str())];
+    let info: PlayerInfo = client.get_wbi(url, &params).await?;
+    let mut subtitles: Vec<SubtitleInfo> = info
+        .subtitle
+        .map(|s| s.subtitles)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|s| !s.subtitle_url.is_empty())
+        .collect();
+    // AI 生成的字幕语言以 ai- 开头
+    subtitles.sort_by_key(|s| (s.lan.starts_with("ai-"), !s.lan.contains("zh")));
+    let Some(subtitle) = subtitles.first() else {
+        return Ok(None);
+    };
+    let subtitle_url = match subtitle.subtitle_url.strip_prefix("//") {
+        Some(url) => format!("https://{url}"),
+        None => subtitle.subtitle_url.clone(),
+    };
+    let body: SubtitleBody = client
+        .http()
+        .get(&subtitle_url)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let lyrics = Lyrics::from_subtitle(body);
+    Ok((!lyrics.is_empty()).then_some(lyrics))
+}
+
+/// 读取歌曲的歌词, 没有缓存时从 B 站获取并保存为 LRC 文件
+pub async fn load_lyrics(client: &BiliClient, track: &Track) -> Result<Option<Lyrics>, AppError> {
+    let path = lyrics_file(track)?;
+    if let Ok(content) = tokio::fs::read_to_string(&path).await {
+        return Ok(Some(Lyrics::from_lrc(&content)));
+    }
+    let Some(lyrics) = fetch_lyrics(client, track).await? else {
+        return Ok(None);
+    };
+    tokio::fs::create_dir_all(lyrics_dir()?).await?;
+    tokio::fs::write(&path, lyrics.to_lrc()).await?;
+    Ok(Some(lyrics))
+}
@@ -1,17 +1,35 @@
-use std::fmt::Display;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
 
+use chrono::{DateTime, Local, TimeZone};
 use colored::Colorize;
-use rand::seq::IteratorRandom;
+use rand::seq::{IndexedRandom, SliceRandom};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use zbus::zvariant::Type;
 
-use crate::{error::AppError, utils::save_current_play_info};
+use crate::{
+    error::AppError,
+    utils::{
+        format_duration, parse_date, save_current_play_info, CURRENT_PLAY_INFO_VERSION,
+        PLAYLIST_VERSION,
+    },
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Copy)]
-#[serde(rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
 pub enum PlayMode {
     Loop,
     Shuffle,
     Repeat,
+    /// 顺序播放, 播放到列表末尾后停止
+    Sequential,
+    /// 按顺序播放整个合集, 合集之间随机选择
+    ShuffleSeasons,
+    /// 加权随机, 偏向评分高、播放次数多的歌曲, 降低最近播放过的歌曲的权重
+    Weighted,
 }
 
 impl From<String> for PlayMode {
@@ -20,6 +38,9 @@ impl From<String> for PlayMode {
             "loop" => PlayMode::Loop,
             "shuffle" => PlayMode::Shuffle,
             "repeat" => PlayMode::Repeat,
+            "sequential" => PlayMode::Sequential,
+            "shuffleseasons" | "shuffle_seasons" => PlayMode::ShuffleSeasons,
+            "weighted" => PlayMode::Weighted,
             _ => PlayMode::Loop,
         }
     }
@@ -31,22 +52,132 @@ impl Display for PlayMode {
             PlayMode::Loop => write!(f, "顺序循环"),
             PlayMode::Shuffle => write!(f, "随机"),
             PlayMode::Repeat => write!(f, "单曲循环"),
+            PlayMode::Sequential => write!(f, "顺序播放"),
+            PlayMode::ShuffleSeasons => write!(f, "合集随机"),
+            PlayMode::Weighted => write!(f, "加权随机"),
         }
     }
 }
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+
+/// 歌曲的唯一标识, 同一个 bvid 的多个分 P 有不同的 cid
+#[derive(Serialize, Deserialize, Type, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TrackId {
+    pub bvid: String,
+    pub cid: String,
+}
+
+impl Display for TrackId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.bvid, self.cid)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
 pub struct Track {
     pub bvid: String,
     pub cid: String,
+    /// 所属合集的 ID
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sid: Option<String>,
+    /// 所属系列的 ID, 与合集 ID 不是同一套编号, 不能混用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series_id: Option<String>,
     pub title: String,
     pub owner: String,
+    /// 视频封面的地址
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub cover: String,
+    /// 时长 (秒), 多 P 视频为该分 P 的时长
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    /// 视频发布时间 (unix 时间戳, 秒)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pubdate: Option<i64>,
+    /// 分区 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tid: Option<u32>,
+    /// 分区名称
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tname: Option<String>,
+    /// 导入时的播放量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub views: Option<u64>,
+    /// 视频简介
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub desc: Option<String>,
+    /// 多 P 视频的分 P 序号, 单 P 视频为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// 用户评分 (1-5)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    /// 是否标记为喜欢
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub loved: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 添加到歌曲列表的时间 (unix 时间戳, 秒)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_at: Option<i64>,
+    /// 视频已失效时记录原因, 播放时会跳过
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unavailable: Option<Unavailable>,
+}
+
+/// 视频无法播放的原因
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnavailableReason {
+    Deleted,
+    Private,
+    RegionLocked,
+    /// 旧版本在登录失效时会记录该原因, 现在登录状态不再记入歌曲, 登录成功后清除
+    NeedsLogin,
+}
+
+impl Display for UnavailableReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnavailableReason::Deleted => write!(f, "已删除"),
+            UnavailableReason::Private => write!(f, "不可见"),
+            UnavailableReason::RegionLocked => write!(f, "地区限制"),
+            UnavailableReason::NeedsLogin => write!(f, "需要登录"),
+        }
+    }
+}
+
+impl UnavailableReason {
+    /// 判断接口错误是否说明视频已失效, 网络错误等临时问题返回 None.
+    /// 未登录或登录失效影响所有歌曲, 重新登录后即可恢复, 同样返回 None
+    pub fn from_error(error: &AppError) -> Option<Self> {
+        match error {
+            AppError::VideoDeleted(_) => Some(UnavailableReason::Deleted),
+            AppError::VideoPrivate(_) => Some(UnavailableReason::Private),
+            AppError::RegionLocked(_) => Some(UnavailableReason::RegionLocked),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Unavailable {
+    pub reason: UnavailableReason,
+    /// 发现失效的时间 (unix 时间戳, 秒)
+    pub marked_at: i64,
+}
+
+impl Unavailable {
+    pub fn marked_at_local(&self) -> DateTime<Local> {
+        Local
+            .timestamp_opt(self.marked_at, 0)
+            .single()
+            .unwrap_or_else(Local::now)
+    }
 }
 
 impl Track {
     pub fn to_println_string(&self) -> String {
-        format!(
+        let mut s = format!(
             "{} {}, {} {}, {} {}, {} {}",
             "bvid:".black(),
             self.bvid.yellow(),
@@ -56,13 +187,128 @@ impl Track {
             self.title.cyan(),
             "owner:".black(),
             self.owner
-        )
+        );
+        if let Some(page) = self.page {
+            s.push_str(&format!(" {}", format!("P{page}").blue()));
+        }
+        if let Some(duration) = self.duration {
+            s.push_str(&format!(" [{}]", format_duration(duration)));
+        }
+        if let Some(tname) = &self.tname {
+            s.push_str(&format!(" {}", tname.magenta()));
+        }
+        if let Some(views) = self.views {
+            s.push_str(&format!(" {}", format!("{views} 播放").black()));
+        }
+        if let Some(pubdate) = self.pubdate_local() {
+            s.push_str(&format!(
+                " {}",
+                pubdate.format("%Y-%m-%d").to_string().black()
+            ));
+        }
+        if self.loved {
+            s.push_str(&format!(" {}", "♥".red()));
+        }
+        if let Some(rating) = self.rating {
+            s.push_str(&format!(" {}", "★".repeat(rating.into()).yellow()));
+        }
+        if !self.tags.is_empty() {
+            s.push_str(&format!(
+                " {}",
+                format!("#{}", self.tags.join(" #")).green()
+            ));
+        }
+        if let Some(unavailable) = &self.unavailable {
+            s.push_str(&format!(
+                " {}",
+                format!("[不可用: {}]", unavailable.reason).red()
+            ));
+        }
+        s
+    }
+
+    pub fn pubdate_local(&self) -> Option<DateTime<Local>> {
+        self.pubdate
+            .and_then(|t| Local.timestamp_opt(t, 0).single())
+    }
+
+    pub fn id(&self) -> TrackId {
+        TrackId {
+            bvid: self.bvid.clone(),
+            cid: self.cid.clone(),
+        }
+    }
+
+    /// 是否为同一首歌曲 (bvid 和 cid 都相同)
+    pub fn has_id(&self, id: &TrackId) -> bool {
+        self.bvid == id.bvid && self.cid == id.cid
+    }
+
+    /// 歌曲所属的合集或系列
+    pub fn collection(&self) -> Option<(CollectionKind, &str)> {
+        self.sid
+            .as_deref()
+            .map(|sid| (CollectionKind::Season, sid))
+            .or_else(|| {
+                self.series_id
+                    .as_deref()
+                    .map(|id| (CollectionKind::Series, id))
+            })
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub fn apply_field(&mut self, field: &TrackField) {
+        match field {
+            TrackField::Title(title) => self.title.clone_from(title),
+            TrackField::Rating(rating) => self.rating = *rating,
+            TrackField::Loved(loved) => self.loved = *loved,
+            TrackField::AddTags(tags) => {
+                for tag in tags {
+                    if !self.has_tag(tag) {
+                        self.tags.push(tag.clone());
+                    }
+                }
+            }
+            TrackField::RemoveTags(tags) => self.tags.retain(|tag| !tags.contains(tag)),
+            TrackField::Unavailable(unavailable) => self.unavailable.clone_from(unavailable),
+        }
+    }
+
+    /// 重新导入时保留用户设置的评分、喜欢、标签和添加时间
+    pub fn keep_user_meta(&mut self, old: &Track) {
+        self.rating = old.rating;
+        self.loved = old.loved;
+        self.tags.clone_from(&old.tags);
+        self.added_at = old.added_at;
+    }
+}
+
+/// 合集的类型, B 站的 UGC 合集和旧版的系列 (列表)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionKind {
+    #[default]
+    Season,
+    Series,
+}
+
+impl Display for CollectionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CollectionKind::Season => write!(f, "合集"),
+            CollectionKind::Series => write!(f, "系列"),
+        }
     }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Season {
     pub id: String,
+    #[serde(default)]
+    pub kind: CollectionKind,
     pub title: String,
     pub cover: String,
     pub intro: String,
@@ -72,7 +318,8 @@ pub struct Season {
 impl Season {
     pub fn to_println_string(&self) -> String {
         format!(
-            "{} {}, {} {}, {} {}, {} {}",
+            "[{}] {} {}, {} {}, {} {}, {} {}",
+            self.kind,
             "sid:".black(),
             self.id.yellow(),
             "title:".black(),
@@ -85,41 +332,537 @@ impl Season {
     }
 }
 
-#[derive(Default, Serialize, Deserialize, Clone)]
+/// 导入来源的类型
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Favorite,
+    Season,
+    Series,
+    Owner,
+}
+
+impl Display for SourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceKind::Favorite => write!(f, "收藏夹"),
+            SourceKind::Season => write!(f, "合集"),
+            SourceKind::Series => write!(f, "系列"),
+            SourceKind::Owner => write!(f, "UP 主"),
+        }
+    }
+}
+
+impl From<CollectionKind> for SourceKind {
+    fn from(kind: CollectionKind) -> Self {
+        match kind {
+            CollectionKind::Season => SourceKind::Season,
+            CollectionKind::Series => SourceKind::Series,
+        }
+    }
+}
+
+/// 导入来源 (收藏夹、合集、系列或 UP 主的投稿), `rsg sync` 时与上次同步的状态对比
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Source {
+    pub kind: SourceKind,
+    /// fid, sid 或 mid
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    /// 只同步标题包含该关键词的投稿, 仅用于 UP 主
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyword: Option<String>,
+    /// 只同步该日期 (YYYY-MM-DD) 之后的投稿, 仅用于 UP 主
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synced_at: Option<i64>,
+    /// 上次同步时来源中的视频
+    #[serde(default)]
+    pub bvids: Vec<String>,
+}
+
+impl Source {
+    pub fn new(kind: SourceKind, id: String, title: String) -> Self {
+        Source {
+            kind,
+            id,
+            title,
+            keyword: None,
+            since: None,
+            synced_at: None,
+            bvids: Vec::new(),
+        }
+    }
+
+    pub fn is_same(&self, other: &Source) -> bool {
+        self.kind == other.kind && self.id == other.id
+    }
+
+    /// 记录来源中当前的视频, 去除重复的 bvid
+    pub fn set_bvids<'a>(&mut self, bvids: impl IntoIterator<Item = &'a str>) {
+        self.bvids.clear();
+        for bvid in bvids {
+            if !self.bvids.iter().any(|b| b == bvid) {
+                self.bvids.push(bvid.to_string());
+            }
+        }
+    }
+
+    pub fn to_println_string(&self) -> String {
+        let mut s = format!(
+            "[{}] {} {}, {} {}, {} {}",
+            self.kind,
+            "id:".black(),
+            self.id.yellow(),
+            "title:".black(),
+            self.title.cyan(),
+            "videos:".black(),
+            self.bvids.len()
+        );
+        if let Some(keyword) = &self.keyword {
+            s.push_str(&format!(", {} {}", "keyword:".black(), keyword));
+        }
+        if let Some(since) = &self.since {
+            s.push_str(&format!(", {} {}", "since:".black(), since));
+        }
+        if let Some(synced_at) = self
+            .synced_at
+            .and_then(|t| Local.timestamp_opt(t, 0).single())
+        {
+            s.push_str(&format!(
+                ", {} {}",
+                "synced:".black(),
+                synced_at.format("%Y-%m-%d %H:%M")
+            ));
+        }
+        s
+    }
+}
+
+/// 由规则定义的智能列表, 所有规则都满足 (或 `match_any` 时任意一条满足) 的歌曲属于该列表
+///
+/// 定义在 `playlists/smart.toml` 中, 例如:
+///
+/// ```toml
+/// [[smart]]
+/// name = "focus"
+/// tag = "focus"
+/// rating_min = 4
+/// added_after = "2024-01-01"
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SmartPlaylist {
+    pub name: String,
+    #[serde(default)]
+    pub match_any: bool,
+    pub owner_contains: Option<String>,
+    pub title_regex: Option<String>,
+    /// 属于其中任意一个合集
+    pub sid_in: Option<Vec<String>>,
+    /// 属于其中任意一个系列, 系列与合集的 ID 不是同一套编号, 所以单独列出
+    pub series_id_in: Option<Vec<String>>,
+    pub tag: Option<String>,
+    pub rating_min: Option<u8>,
+    /// 格式为 `YYYY-MM-DD`
+    pub added_after: Option<String>,
+    pub play_count_min: Option<u32>,
+    pub play_count_max: Option<u32>,
+    /// 规则计算出的歌曲, 不写入文件
+    #[serde(skip)]
+    pub tracks: Vec<Track>,
+}
+
+impl SmartPlaylist {
+    pub fn evaluate(
+        &self,
+        tracks: &[Track],
+        play_counts: &HashMap<TrackId, u32>,
+    ) -> Result<Vec<Track>, AppError> {
+        let title_regex = self
+            .title_regex
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| AppError::InvalidInput(format!("智能列表 [{}]: {e}", self.name)))?;
+        let added_after = self
+            .added_after
+            .as_deref()
+            .map(|d| {
+                parse_date(d).ok_or_else(|| {
+                    AppError::InvalidInput(format!("智能列表 [{}]: 无效的日期 {d}", self.name))
+                })
+            })
+            .transpose()?;
+
+        let matches = |track: &Track| {
+            let play_count = play_counts.get(&track.id()).copied().unwrap_or(0);
+            let rules = [
+                self.owner_contains
+                    .as_ref()
+                    .map(|owner| track.owner.contains(owner)),
+                title_regex.as_ref().map(|re| re.is_match(&track.title)),
+                self.sid_in
+                    .as_ref()
+                    .map(|sids| track.sid.as_ref().is_some_and(|sid| sids.contains(sid))),
+                self.series_id_in
+                    .as_ref()
+                    .map(|ids| track.series_id.as_ref().is_some_and(|id| ids.contains(id))),
+                self.tag.as_ref().map(|tag| track.has_tag(tag)),
+                self.rating_min
+                    .map(|min| track.rating.is_some_and(|r| r >= min)),
+                added_after.map(|after| track.added_at.is_some_and(|t| t >= after)),
+                self.play_count_min.map(|min| play_count >= min),
+                self.play_count_max.map(|max| play_count <= max),
+            ];
+            let mut rules = rules.into_iter().flatten().peekable();
+            if rules.peek().is_none() {
+                return false;
+            }
+            if self.match_any {
+                rules.any(|matched| matched)
+            } else {
+                rules.all(|matched| matched)
+            }
+        };
+        Ok(tracks.iter().filter(|t| matches(t)).cloned().collect())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SmartPlaylists {
+    #[serde(default)]
+    pub smart: Vec<SmartPlaylist>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Playlist {
+    /// 文件格式的版本, 包括其中 `Track` 的格式, 见 `utils::PLAYLIST_VERSION`
+    #[serde(default)]
+    pub version: u32,
     pub tracks: Vec<Track>,
     pub seasons: Vec<Season>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<Source>,
+    /// 智能列表定义在单独的文件中, 由 `refresh_smart_playlists` 填充
+    #[serde(skip)]
+    pub smart_playlists: Vec<SmartPlaylist>,
+}
+
+impl Default for Playlist {
+    fn default() -> Self {
+        Self {
+            version: PLAYLIST_VERSION,
+            tracks: Vec::new(),
+            seasons: Vec::new(),
+            sources: Vec::new(),
+            smart_playlists: Vec::new(),
+        }
+    }
 }
 
 impl Playlist {
-    pub fn find_tracks_in_season(&self, sid: &str) -> Vec<Track> {
+    /// 将歌曲标记为失效, 同一视频的所有分 P 一起标记, 返回是否有歌曲被修改
+    pub fn mark_unavailable(&mut self, bvid: &str, reason: UnavailableReason) -> bool {
+        let marked_at = Local::now().timestamp();
+        let mut changed = false;
+        for track in self.tracks.iter_mut().filter(|t| t.bvid == bvid) {
+            if track.unavailable.as_ref().map(|u| u.reason) != Some(reason) {
+                track.unavailable = Some(Unavailable { reason, marked_at });
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// 视频恢复可以播放时清除失效标记, 返回是否有歌曲被修改
+    pub fn clear_unavailable(&mut self, bvid: &str) -> bool {
+        let mut changed = false;
+        for track in self.tracks.iter_mut().filter(|t| t.bvid == bvid) {
+            changed |= track.unavailable.take().is_some();
+        }
+        changed
+    }
+
+    pub fn unavailable_tracks(&self) -> Vec<&Track> {
+        self.tracks
+            .iter()
+            .filter(|t| t.unavailable.is_some())
+            .collect()
+    }
+
+    pub fn find_season(&self, kind: CollectionKind, id: &str) -> Option<&Season> {
+        self.seasons.iter().find(|s| s.kind == kind && s.id == id)
+    }
+
+    pub fn find_tracks_in_season(&self, kind: CollectionKind, id: &str) -> Vec<Track> {
+        self.tracks
+            .iter()
+            .filter(|t| t.collection() == Some((kind, id)))
+            .cloned()
+            .collect::<Vec<Track>>()
+    }
+
+    pub fn find_tracks_in_smart(&self, name: &str) -> Vec<Track> {
+        self.smart_playlists
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.tracks.clone())
+            .unwrap_or_default()
+    }
+
+    /// 重新计算所有智能列表包含的歌曲, 规则无效的列表为空
+    pub fn refresh_smart_playlists(
+        &mut self,
+        smart_playlists: Vec<SmartPlaylist>,
+        play_counts: &HashMap<TrackId, u32>,
+    ) {
+        self.smart_playlists = smart_playlists;
+        for smart in &mut self.smart_playlists {
+            smart.tracks = smart
+                .evaluate(&self.tracks, play_counts)
+                .unwrap_or_else(|e| {
+                    log::error!("Failed to evaluate smart playlist: {}", e);
+                    Vec::new()
+                });
+        }
+    }
+
+    pub fn find_track(&self, id: &TrackId) -> Option<&Track> {
+        self.tracks.iter().find(|t| t.has_id(id))
+    }
+
+    /// 歌曲的封面地址, 歌曲没有封面时使用所属合集的封面, 都没有时为空字符串
+    pub fn cover_of<'a>(&'a self, track: &'a Track) -> &'a str {
+        let track = self.find_track(&track.id()).unwrap_or(track);
+        if !track.cover.is_empty() {
+            return &track.cover;
+        }
+        track
+            .collection()
+            .and_then(|(kind, id)| self.find_season(kind, id))
+            .map_or("", |season| season.cover.as_str())
+    }
+
+    /// 将 bvid (和可选的 cid) 解析为歌曲标识, 不指定 cid 时取该 bvid 的第一个分 P
+    pub fn resolve_track_id(&self, bvid: &str, cid: Option<&str>) -> Option<TrackId> {
         self.tracks
-            .clone()
-            .into_iter()
-            .filter(|t| t.sid == Some(sid.to_string()))
+            .iter()
+            .find(|t| t.bvid == bvid && cid.is_none_or(|cid| t.cid == cid))
+            .map(Track::id)
+    }
+
+    pub fn find_tracks_with_tag(&self, tag: &str) -> Vec<Track> {
+        self.tracks
+            .iter()
+            .filter(|t| t.has_tag(tag))
+            .cloned()
             .collect::<Vec<Track>>()
     }
+
+    /// 合并导入的歌曲, 已存在的歌曲保留评分、标签等信息; 导入完整的合集时,
+    /// 替换之前从该合集导入的歌曲. 返回新增的歌曲数量
+    pub fn import_source(&mut self, import: SourceImport) -> usize {
+        let SourceImport {
+            mut tracks,
+            season,
+            source,
+        } = import;
+        let now = Local::now().timestamp();
+        let mut added = 0;
+        for new_track in &mut tracks {
+            let id = new_track.id();
+            if let Some(old) = self.tracks.iter().find(|t| t.has_id(&id)) {
+                new_track.keep_user_meta(old);
+            } else {
+                new_track.added_at = Some(now);
+                added += 1;
+            }
+        }
+        let new_track_ids: HashSet<TrackId> = tracks.iter().map(Track::id).collect();
+        self.tracks.retain(|t| !new_track_ids.contains(&t.id()));
+        if let Some(season) = &season {
+            let key = Some((season.kind, season.id.as_str()));
+            if tracks.iter().any(|t| t.collection() == key) {
+                self.tracks.retain(|t| t.collection() != key);
+            }
+        }
+        self.tracks.extend(tracks);
+        if let Some(season) = season {
+            self.seasons
+                .retain(|s| !(s.kind == season.kind && s.id == season.id));
+            self.seasons.push(season);
+        }
+        if let Some(source) = source {
+            self.sources.retain(|s| !s.is_same(&source));
+            self.sources.push(source);
+        }
+        added
+    }
+
+    /// 按字段修改列表中的歌曲, 没有修改的字段保持列表中的值, 返回被修改的歌曲数量
+    pub fn update_tracks(&mut self, patches: &[TrackPatch]) -> usize {
+        let mut updated = 0;
+        for patch in patches {
+            if let Some(track) = self.tracks.iter_mut().find(|t| t.has_id(&patch.id)) {
+                let old = track.clone();
+                for field in &patch.fields {
+                    track.apply_field(field);
+                }
+                if *track != old {
+                    updated += 1;
+                }
+            }
+        }
+        updated
+    }
+
+    /// 删除歌曲和导入来源, 同时删除已经没有歌曲的合集, 返回删除的歌曲、合集和来源的总数
+    pub fn remove_tracks(&mut self, removal: &TrackRemoval) -> usize {
+        let ids: HashSet<&TrackId> = removal.ids.iter().collect();
+        let old_len = self.tracks.len() + self.seasons.len() + self.sources.len();
+        self.tracks.retain(|t| !ids.contains(&t.id()));
+        self.sources
+            .retain(|s| !removal.sources.iter().any(|r| r.is_same(s)));
+        let exists_seasons: HashSet<(CollectionKind, &str)> =
+            self.tracks.iter().filter_map(Track::collection).collect();
+        self.seasons
+            .retain(|s| exists_seasons.contains(&(s.kind, s.id.as_str())));
+        self.sources.retain(|s| {
+            let kind = match s.kind {
+                SourceKind::Season => CollectionKind::Season,
+                SourceKind::Series => CollectionKind::Series,
+                _ => return true,
+            };
+            exists_seasons.contains(&(kind, s.id.as_str()))
+        });
+        old_len - (self.tracks.len() + self.seasons.len() + self.sources.len())
+    }
+
+    /// 应用一次修改, 返回受影响的数量, 为 0 时歌曲列表没有变化
+    pub fn apply(&mut self, change: PlaylistChange) -> usize {
+        match change {
+            PlaylistChange::ImportSource(import) => self.import_source(*import),
+            PlaylistChange::UpdateTracks(patches) => self.update_tracks(&patches),
+            PlaylistChange::RemoveTracks(removal) => self.remove_tracks(&removal),
+            PlaylistChange::Batch(changes) => changes.into_iter().map(|c| self.apply(c)).sum(),
+        }
+    }
 }
 
+/// 一次导入的结果, 添加单个视频时没有合集和来源
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct SourceImport {
+    pub tracks: Vec<Track>,
+    pub season: Option<Season>,
+    pub source: Option<Source>,
+}
+
+/// 歌曲中可以单独修改的字段, 标签按增删合并
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackField {
+    Title(String),
+    Rating(Option<u8>),
+    Loved(bool),
+    AddTags(Vec<String>),
+    RemoveTags(Vec<String>),
+    Unavailable(Option<Unavailable>),
+}
+
+/// 对一首歌曲的修改, 只包含变化的字段, 不会覆盖 rosesong 同时做的其他修改
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrackPatch {
+    pub id: TrackId,
+    pub fields: Vec<TrackField>,
+}
+
+/// 要删除的歌曲和不再关注的导入来源
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct TrackRemoval {
+    pub ids: Vec<TrackId>,
+    pub sources: Vec<Source>,
+}
+
+/// 对歌曲列表的修改. rosesong 运行时由 rsg 通过 D-Bus 交给 rosesong 执行,
+/// 否则 rsg 直接修改歌曲列表文件
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistChange {
+    ImportSource(Box<SourceImport>),
+    UpdateTracks(Vec<TrackPatch>),
+    RemoveTracks(TrackRemoval),
+    /// 多个修改一起应用并保存, 中途不会留下只修改了一部分的歌曲列表
+    Batch(Vec<PlaylistChange>),
+}
+
+/// 当前播放列表的来源
+#[derive(Clone, Debug, PartialEq)]
+pub enum TrackSelection {
+    All,
+    Season(String),
+    Series(String),
+    Tag(String),
+    Smart(String),
+}
+
+/// 播放历史最多保留的条数
+const MAX_HISTORY_LEN: usize = 100;
+/// 加权随机时参与降权的最近播放歌曲数量
+const RECENT_WINDOW: usize = 20;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CurrentPlayInfo {
+    /// 文件格式的版本, 见 `utils::CURRENT_PLAY_INFO_VERSION`
+    #[serde(default)]
+    pub version: u32,
     pub index: usize,
     pub volume: usize,
     pub play_mode: PlayMode,
     pub track: Option<Track>,
     pub playing_sid: Option<String>,
+    /// 当前只播放该系列中的歌曲
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playing_series: Option<String>,
+    /// 当前只播放带有该标签的歌曲
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playing_tag: Option<String>,
+    /// 当前只播放该智能列表中的歌曲
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playing_smart: Option<String>,
     pub current_tracks: Vec<Track>,
+    /// 随机播放顺序 (`current_tracks` 的索引排列), 播放完一轮后重新打乱
+    #[serde(default)]
+    pub shuffle_order: Vec<usize>,
+    /// 下一首在 `shuffle_order` 中的位置
+    #[serde(default)]
+    pub shuffle_position: usize,
+    /// 实际播放过的歌曲索引, 用于 `previous` 回退
+    #[serde(default)]
+    pub history: Vec<usize>,
+    /// 每首歌曲完整播放的次数, 由播放历史统计得到, 不写入文件
+    #[serde(skip)]
+    pub play_counts: HashMap<TrackId, u32>,
 }
 
 impl Default for CurrentPlayInfo {
     fn default() -> Self {
         Self {
+            version: CURRENT_PLAY_INFO_VERSION,
             index: 0,
             volume: 100,
             play_mode: PlayMode::Loop,
             track: None,
             playing_sid: None,
+            playing_series: None,
+            playing_tag: None,
+            playing_smart: None,
             current_tracks: Vec::new(),
+            shuffle_order: Vec::new(),
+            shuffle_position: 0,
+            history: Vec::new(),
+            play_counts: HashMap::new(),
         }
     }
 }
@@ -146,19 +889,125 @@ impl CurrentPlayInfo {
         Ok(())
     }
 
+    pub fn selection(&self) -> TrackSelection {
+        if let Some(sid) = &self.playing_sid {
+            TrackSelection::Season(sid.clone())
+        } else if let Some(id) = &self.playing_series {
+            TrackSelection::Series(id.clone())
+        } else if let Some(tag) = &self.playing_tag {
+            TrackSelection::Tag(tag.clone())
+        } else if let Some(name) = &self.playing_smart {
+            TrackSelection::Smart(name.clone())
+        } else {
+            TrackSelection::All
+        }
+    }
+
+    pub fn set_selection(&mut self, selection: TrackSelection) {
+        self.playing_sid = None;
+        self.playing_series = None;
+        self.playing_tag = None;
+        self.playing_smart = None;
+        match selection {
+            TrackSelection::All => {}
+            TrackSelection::Season(sid) => self.playing_sid = Some(sid),
+            TrackSelection::Series(id) => self.playing_series = Some(id),
+            TrackSelection::Tag(tag) => self.playing_tag = Some(tag),
+            TrackSelection::Smart(name) => self.playing_smart = Some(name),
+        }
+    }
+
+    /// 根据当前播放的合集、标签或智能列表从歌曲列表中选出要播放的歌曲, 跳过已失效的歌曲,
+    /// 如果选不出任何歌曲则改为播放全部可以播放的歌曲
+    pub fn select_tracks(&mut self, playlist: &Playlist) -> Vec<Track> {
+        let available = |tracks: Vec<Track>| -> Vec<Track> {
+            tracks
+                .into_iter()
+                .filter(|t| t.unavailable.is_none())
+                .collect()
+        };
+        let tracks = match self.selection() {
+            TrackSelection::All => return available(playlist.tracks.clone()),
+            TrackSelection::Season(sid) => {
+                available(playlist.find_tracks_in_season(CollectionKind::Season, &sid))
+            }
+            TrackSelection::Series(id) => {
+                available(playlist.find_tracks_in_season(CollectionKind::Series, &id))
+            }
+            TrackSelection::Tag(tag) => available(playlist.find_tracks_with_tag(&tag)),
+            TrackSelection::Smart(name) => available(playlist.find_tracks_in_smart(&name)),
+        };
+        if tracks.is_empty() {
+            self.set_selection(TrackSelection::All);
+            return available(playlist.tracks.clone());
+        }
+        tracks
+    }
+
+    /// 替换当前播放的歌曲, 歌曲发生变化时之前的随机顺序和播放历史中的索引已经失效
+    pub fn replace_tracks(&mut self, tracks: Vec<Track>) {
+        let same_tracks = self.current_tracks.len() == tracks.len()
+            && self
+                .current_tracks
+                .iter()
+                .zip(&tracks)
+                .all(|(a, b)| a.has_id(&b.id()));
+        if !same_tracks {
+            self.reset_play_order();
+        }
+        self.current_tracks = tracks;
+    }
+
+    /// 跳转到指定歌曲, 并把当前歌曲记入播放历史
+    pub async fn jump_to(&mut self, index: usize) -> Result<(), AppError> {
+        self.record_jump(index);
+        self.set_current(index).await
+    }
+
+    /// 记录跳转: 当前歌曲记入播放历史, 随机模式下把目标歌曲移到随机顺序的游标处,
+    /// 使 `previous` 回退时对 `shuffle_position` 的调整与历史保持一致
+    fn record_jump(&mut self, index: usize) {
+        if index == self.index {
+            return;
+        }
+        self.push_history(self.index);
+        if self.play_mode != PlayMode::Shuffle
+            || self.shuffle_order.len() != self.current_tracks.len()
+        {
+            return;
+        }
+        let Some(pos) = self.shuffle_order.iter().position(|&i| i == index) else {
+            return;
+        };
+        if pos >= self.shuffle_position {
+            // 本轮尚未播放: 与游标处交换并前移游标
+            self.shuffle_order.swap(pos, self.shuffle_position);
+            self.shuffle_position += 1;
+        } else {
+            // 本轮已经播放过: 移到已播放部分的末尾, 游标不变
+            let track = self.shuffle_order.remove(pos);
+            self.shuffle_order.insert(self.shuffle_position - 1, track);
+        }
+    }
+
     pub fn get_current_track(&self) -> Option<Track> {
         self.current_tracks.get(self.index).cloned()
     }
 
-    pub fn find_track_index(&self, bvid: &str) -> Option<usize> {
+    pub fn find_track_index(&self, id: &TrackId) -> Option<usize> {
         self.current_tracks
             .iter()
-            .position(|track| track.bvid == bvid)
+            .position(|track| track.has_id(id))
     }
 
-    pub async fn move_to_next_track(&mut self) -> Result<(), AppError> {
+    /// 切换到下一首, 返回 `false` 表示列表已经播放完毕 (顺序播放模式) 或播放列表为空
+    pub async fn move_to_next_track(&mut self) -> Result<bool, AppError> {
         let current_index = self.index;
         let current_tracks_len = self.current_tracks.len();
+        if current_tracks_len == 0 {
+            log::info!("current tracks is empty, no next track");
+            return Ok(false);
+        }
         log::info!(
             "move to next track, current index: {}, current tracks len: {}",
             current_index,
@@ -166,25 +1015,51 @@ impl CurrentPlayInfo {
         );
         let new_index = match self.play_mode {
             PlayMode::Loop => (current_index + 1) % current_tracks_len,
-            PlayMode::Shuffle => {
-                let mut rng = rand::rng();
-                (0..current_tracks_len).choose(&mut rng).ok_or_else(|| {
-                    AppError::DataParsing("Failed to choose random track".to_string())
-                })?
-            }
+            PlayMode::Shuffle => self.next_shuffle_index().ok_or_else(|| {
+                AppError::DataParsing("Failed to choose random track".to_string())
+            })?,
             PlayMode::Repeat => current_index,
+            PlayMode::Sequential => {
+                if current_index + 1 >= current_tracks_len {
+                    log::info!("reached the end of current tracks");
+                    return Ok(false);
+                }
+                current_index + 1
+            }
+            PlayMode::ShuffleSeasons => self.next_season_index().ok_or_else(|| {
+                AppError::DataParsing("Failed to choose random season".to_string())
+            })?,
+            PlayMode::Weighted => self.next_weighted_index().ok_or_else(|| {
+                AppError::DataParsing("Failed to choose weighted random track".to_string())
+            })?,
         };
         log::info!("move to next track, new index: {}", new_index);
+        if new_index != current_index {
+            self.push_history(current_index);
+        }
         self.index = new_index;
         self.track = self.current_tracks.get(new_index).cloned();
         save_current_play_info(self).await?;
-        Ok(())
+        Ok(true)
     }
 
+    /// 切换到上一首, 播放列表为空时不做任何修改
     pub async fn move_to_previous_track(&mut self) -> Result<(), AppError> {
+        if self.current_tracks.is_empty() {
+            return Ok(());
+        }
+        let new_index = self.previous_index();
+        self.index = new_index;
+        self.track = self.current_tracks.get(new_index).cloned();
+        save_current_play_info(self).await?;
+        Ok(())
+    }
+
+    /// 根据播放模式计算上一首, 随机类模式从播放历史中回退
+    fn previous_index(&mut self) -> usize {
         let current_index = self.index;
         let current_tracks_len = self.current_tracks.len();
-        let new_index = match self.play_mode {
+        match self.play_mode {
             PlayMode::Loop => {
                 if current_index == 0 {
                     current_tracks_len - 1
@@ -192,17 +1067,235 @@ impl CurrentPlayInfo {
                     current_index - 1
                 }
             }
-            PlayMode::Shuffle => {
-                let mut rng = rand::rng();
-                (0..current_tracks_len).choose(&mut rng).ok_or_else(|| {
-                    AppError::DataParsing("Failed to choose random track".to_string())
-                })?
+            PlayMode::Sequential => current_index.saturating_sub(1),
+            PlayMode::Shuffle | PlayMode::ShuffleSeasons | PlayMode::Weighted => {
+                match self.history.pop() {
+                    Some(index) if index < current_tracks_len => {
+                        // 回退后下一首重新播放当前歌曲, 不跳过随机顺序中的歌曲
+                        if self.play_mode == PlayMode::Shuffle {
+                            self.shuffle_position = self.shuffle_position.saturating_sub(1);
+                        }
+                        index
+                    }
+                    _ => current_index,
+                }
             }
             PlayMode::Repeat => current_index,
+        }
+    }
+
+    /// 根据播放模式选择新播放列表的第一首
+    pub fn first_index(&mut self) -> usize {
+        match self.play_mode {
+            PlayMode::Shuffle => self.start_shuffle(),
+            PlayMode::ShuffleSeasons => self.random_season_start(None),
+            PlayMode::Weighted => self.next_weighted_index(),
+            _ => None,
+        }
+        .unwrap_or(0)
+    }
+
+    /// 清空随机播放顺序和播放历史, `current_tracks` 变化后需要调用
+    pub fn reset_play_order(&mut self) {
+        self.shuffle_order.clear();
+        self.shuffle_position = 0;
+        self.history.clear();
+    }
+
+    /// 重新打乱随机播放顺序并返回第一首
+    pub fn start_shuffle(&mut self) -> Option<usize> {
+        self.reshuffle();
+        self.next_shuffle_index()
+    }
+
+    /// 从随机播放顺序中取出下一首, 一轮播放完后重新打乱
+    pub fn next_shuffle_index(&mut self) -> Option<usize> {
+        let len = self.current_tracks.len();
+        if self.shuffle_order.len() != len {
+            // 播放列表变化或刚切换到随机模式, 当前歌曲视为本轮已播放
+            self.reshuffle();
+            if let Some(pos) = self.shuffle_order.iter().position(|&i| i == self.index) {
+                self.shuffle_order.swap(0, pos);
+                self.shuffle_position = 1;
+            }
+        } else if self.shuffle_position >= len {
+            self.reshuffle();
+            // 避免新一轮的第一首与刚播放的歌曲相同
+            if len > 1 && self.shuffle_order[0] == self.index {
+                self.shuffle_order.swap(0, len - 1);
+            }
+        }
+        let index = self.shuffle_order.get(self.shuffle_position).copied()?;
+        self.shuffle_position += 1;
+        Some(index)
+    }
+
+    /// 将 `current_tracks` 按合集分组, 不属于合集的歌曲各自成为一组
+    fn season_groups(&self) -> Vec<Vec<usize>> {
+        let mut groups: Vec<(_, Vec<usize>)> = Vec::new();
+        for (i, track) in self.current_tracks.iter().enumerate() {
+            let key = track.collection();
+            match groups.iter_mut().find(|(k, _)| key.is_some() && *k == key) {
+                Some((_, indexes)) => indexes.push(i),
+                None => groups.push((key, vec![i])),
+            }
+        }
+        groups.into_iter().map(|(_, indexes)| indexes).collect()
+    }
+
+    /// 随机选择一个合集 (尽量避开 `exclude` 所在的组) 并返回其第一首
+    fn random_season_start(&self, exclude: Option<usize>) -> Option<usize> {
+        let groups = self.season_groups();
+        let candidates = groups
+            .iter()
+            .filter(|g| groups.len() == 1 || exclude.is_none_or(|i| !g.contains(&i)))
+            .collect::<Vec<_>>();
+        candidates
+            .choose(&mut rand::rng())
+            .and_then(|g| g.first().copied())
+    }
+
+    /// 合集内顺序播放, 合集播放完后随机切换到另一个合集
+    fn next_season_index(&self) -> Option<usize> {
+        let groups = self.season_groups();
+        let group = groups.iter().find(|g| g.contains(&self.index))?;
+        let pos = group.iter().position(|&i| i == self.index)?;
+        match group.get(pos + 1) {
+            Some(&index) => Some(index),
+            None => self.random_season_start(Some(self.index)),
+        }
+    }
+
+    /// 歌曲的播放权重, 评分和播放次数越高权重越高, 最近播放过的歌曲权重更低
+    fn track_weight(&self, index: usize, recent: &[usize]) -> f64 {
+        if self.current_tracks.len() > 1 && index == self.index {
+            return 0.0;
+        }
+        let Some(track) = self.current_tracks.get(index) else {
+            return 0.0;
         };
-        self.index = new_index;
-        self.track = self.current_tracks.get(new_index).cloned();
-        save_current_play_info(self).await?;
-        Ok(())
+        let play_count = self.play_counts.get(&track.id()).copied().unwrap_or(0);
+        let mut weight = 1.0 + f64::from(play_count).ln_1p();
+        // 未评分按 3 分计算
+        weight *= f64::from(track.rating.unwrap_or(3)) / 3.0;
+        if track.loved {
+            weight *= 1.5;
+        }
+        match recent.iter().position(|&i| i == index) {
+            #[allow(clippy::cast_precision_loss)]
+            Some(pos) => weight * (pos + 1) as f64 / (RECENT_WINDOW + 1) as f64,
+            None => weight,
+        }
+    }
+
+    fn next_weighted_index(&self) -> Option<usize> {
+        let recent = self
+            .history
+            .iter()
+            .rev()
+            .take(RECENT_WINDOW.min(self.current_tracks.len() / 2))
+            .copied()
+            .collect::<Vec<usize>>();
+        let indexes = (0..self.current_tracks.len()).collect::<Vec<usize>>();
+        indexes
+            .choose_weighted(&mut rand::rng(), |&i| self.track_weight(i, &recent))
+            .ok()
+            .copied()
+    }
+
+    fn reshuffle(&mut self) {
+        let mut order = (0..self.current_tracks.len()).collect::<Vec<usize>>();
+        order.shuffle(&mut rand::rng());
+        self.shuffle_order = order;
+        self.shuffle_position = 0;
+    }
+
+    fn push_history(&mut self, index: usize) {
+        self.history.push(index);
+        if self.history.len() > MAX_HISTORY_LEN {
+            self.history.remove(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shuffle_info(len: usize) -> CurrentPlayInfo {
+        let tracks = (0..len)
+            .map(|i| Track {
+                bvid: format!("BV{i}"),
+                cid: i.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        CurrentPlayInfo {
+            play_mode: PlayMode::Shuffle,
+            current_tracks: tracks,
+            ..Default::default()
+        }
+    }
+
+    /// 模拟 `move_to_next_track` 的随机分支
+    fn next(info: &mut CurrentPlayInfo) -> usize {
+        let index = info.next_shuffle_index().unwrap();
+        if index != info.index {
+            info.push_history(info.index);
+        }
+        info.index = index;
+        index
+    }
+
+    fn jump(info: &mut CurrentPlayInfo, index: usize) {
+        info.record_jump(index);
+        info.index = index;
+    }
+
+    fn previous(info: &mut CurrentPlayInfo) -> usize {
+        info.index = info.previous_index();
+        info.index
+    }
+
+    #[test]
+    fn previous_after_jump_to_unplayed_track() {
+        let mut info = shuffle_info(6);
+        info.index = info.start_shuffle().unwrap();
+        let first = info.index;
+        let second = next(&mut info);
+        let target = info.shuffle_order[info.shuffle_position + 1];
+        jump(&mut info, target);
+
+        assert_eq!(previous(&mut info), second);
+        // 回退后下一首重新播放跳转到的歌曲, 随机顺序中的其它歌曲不被跳过
+        assert_eq!(next(&mut info), target);
+        let rest = (0..3).map(|_| next(&mut info)).collect::<Vec<_>>();
+        let mut round = vec![first, second, target];
+        round.extend(rest);
+        round.sort_unstable();
+        assert_eq!(round, (0..6).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn previous_after_jump_to_played_track() {
+        let mut info = shuffle_info(6);
+        info.index = info.start_shuffle().unwrap();
+        let first = info.index;
+        let second = next(&mut info);
+        let third = next(&mut info);
+        jump(&mut info, first);
+
+        assert_eq!(previous(&mut info), third);
+        assert_eq!(previous(&mut info), second);
+        // 本轮剩余的歌曲仍会播放, 不会因为跳转提前开始新一轮
+        assert_eq!(info.shuffle_position, 1);
+        assert_eq!(next(&mut info), third);
+        let mut rest = (0..4).map(|_| next(&mut info)).collect::<Vec<_>>();
+        rest.sort_unstable();
+        let mut expected = (0..6)
+            .filter(|&i| i != second && i != third)
+            .collect::<Vec<_>>();
+        expected.sort_unstable();
+        assert_eq!(rest, expected);
     }
 }
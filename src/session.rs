@@ -0,0 +1,109 @@
+use std::os::unix::fs::PermissionsExt;
+
+use reqwest::{
+    header::{HeaderMap, HeaderValue, COOKIE},
+    Client, ClientBuilder, Url,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::{error::AppError, utils::session_file};
+
+/// 扫码登录后保存的 B 站 cookie
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Session {
+    #[serde(rename = "SESSDATA")]
+    pub sessdata: String,
+    pub bili_jct: String,
+    #[serde(rename = "DedeUserID")]
+    pub dede_user_id: String,
+}
+
+impl Session {
+    /// 从扫码登录成功后返回的跳转链接中读取 cookie
+    pub fn from_login_url(url: &str) -> Option<Session> {
+        let url = Url::parse(url).ok()?;
+        let param = |key: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.to_string())
+        };
+        Some(Session {
+            sessdata: param("SESSDATA")?,
+            bili_jct: param("bili_jct")?,
+            dede_user_id: param("DedeUserID")?,
+        })
+    }
+
+    pub fn cookie_header(&self) -> String {
+        // SESSDATA 中的逗号等字符需要保持编码后的形式
+        let sessdata = self.sessdata.replace(',', "%2C").replace('*', "%2A");
+        format!(
+            "SESSDATA={sessdata}; bili_jct={}; DedeUserID={}",
+            self.bili_jct, self.dede_user_id
+        )
+    }
+}
+
+/// 读取保存的登录信息, 未登录或文件损坏时返回 None
+pub fn load_session() -> Option<Session> {
+    let path = session_file().ok()?;
+    let content = std::fs::read_to_string(path).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// 保存登录信息, 文件权限为 0600
+pub async fn save_session(session: &Session) -> Result<(), AppError> {
+    let path = session_file()?;
+    let content = toml::to_string(session)
+        .map_err(|_| AppError::DataParsing("Failed to serialize session to TOML".to_string()))?;
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .await?;
+    // 文件已存在时 mode 不生效, 需要单独设置权限
+    tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).await?;
+    file.write_all(content.as_bytes()).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+/// 删除登录信息, 返回之前是否处于登录状态
+pub async fn remove_session() -> Result<bool, AppError> {
+    let path = session_file()?;
+    if !path.exists() {
+        return Ok(false);
+    }
+    tokio::fs::remove_file(path).await?;
+    Ok(true)
+}
+
+// 搜索等接口要求请求中带有 buvid3 cookie, 格式为 uuid 加 5 位数字和 infoc
+fn random_buvid3() -> String {
+    let hex = format!("{:032X}", rand::random::<u128>());
+    format!(
+        "{}-{}-{}-{}-{}{:05}infoc",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..],
+        rand::random::<u32>() % 100_000
+    )
+}
+
+/// 带有登录 cookie 的 HTTP 客户端, 未登录时只带有随机生成的 buvid3
+pub fn client_builder() -> Result<ClientBuilder, AppError> {
+    let mut cookie = format!("buvid3={}", random_buvid3());
+    if let Some(session) = load_session() {
+        cookie = format!("{cookie}; {}", session.cookie_header());
+    }
+    let mut cookie = HeaderValue::from_str(&cookie)?;
+    cookie.set_sensitive(true);
+    let mut headers = HeaderMap::new();
+    headers.insert(COOKIE, cookie);
+    Ok(Client::builder().default_headers(headers))
+}
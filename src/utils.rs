@@ -1,43 +1,79 @@
-use std::path::PathBuf;
+use std::{
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{Local, NaiveDate};
+use serde::de::DeserializeOwned;
 
 use crate::{
     error::AppError,
-    model::{CurrentPlayInfo, Playlist},
+    model::{CurrentPlayInfo, Playlist, SmartPlaylist, SmartPlaylists},
 };
 
 pub async fn init_dir() -> Result<(), AppError> {
-    let home_dir = std::env::var("HOME")?;
-    let logs_dir = PathBuf::from(format!("{home_dir}/.config/rosesong/logs"));
-    if !logs_dir.exists() {
-        tokio::fs::create_dir_all(&logs_dir).await?;
+    for dir in [config_dir()?, logs_dir()?, playlist_dir()?] {
+        if !dir.exists() {
+            tokio::fs::create_dir_all(&dir).await?;
+        }
     }
-    let playlists_dir = PathBuf::from(format!("{home_dir}/.config/rosesong/playlists"));
-    if !playlists_dir.exists() {
-        tokio::fs::create_dir_all(&playlists_dir).await?;
-    }
-    let playlist_path = playlists_dir.join("playlist.toml");
+    let playlist_path = playlist_file()?;
     if !playlist_path.exists() {
         let content = toml::to_string(&Playlist::default())
             .map_err(|_| AppError::DataParsing("Failed to serialize tracks to TOML".to_string()))?;
-        tokio::fs::write(&playlist_path, content).await?;
+        write_atomic(&playlist_path, content).await?;
     }
     Ok(())
 }
 
-pub fn app_dir() -> Result<PathBuf, AppError> {
-    let home_dir = std::env::var("HOME")?;
-    let app_dir = PathBuf::from(format!("{home_dir}/.config/rosesong"));
-    Ok(app_dir)
+// 设置了 ROSESONG_HOME 时所有文件都放在该目录下, 便于便携使用和测试
+fn rosesong_home() -> Option<PathBuf> {
+    std::env::var_os("ROSESONG_HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+// XDG 基础目录, 环境变量未设置或不是绝对路径时使用 `$HOME` 下的默认目录
+fn xdg_dir(var: &str, default: &str) -> Result<PathBuf, AppError> {
+    if let Some(home) = rosesong_home() {
+        return Ok(home);
+    }
+    let dir = match std::env::var_os(var).map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => PathBuf::from(std::env::var("HOME")?).join(default),
+    };
+    Ok(dir.join("rosesong"))
+}
+
+/// 配置文件所在目录, 默认为 `~/.config/rosesong`
+pub fn config_dir() -> Result<PathBuf, AppError> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// 歌曲列表等数据所在目录, 默认为 `~/.local/share/rosesong`
+pub fn data_dir() -> Result<PathBuf, AppError> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// 播放状态、播放记录和日志所在目录, 默认为 `~/.local/state/rosesong`
+pub fn state_dir() -> Result<PathBuf, AppError> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// 封面和歌词缓存所在目录, 默认为 `~/.cache/rosesong`
+pub fn cache_dir() -> Result<PathBuf, AppError> {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
 }
 
 pub fn logs_dir() -> Result<PathBuf, AppError> {
-    let app_dir = app_dir()?;
-    Ok(app_dir.join("logs"))
+    let state_dir = state_dir()?;
+    Ok(state_dir.join("logs"))
 }
 
 pub fn playlist_dir() -> Result<PathBuf, AppError> {
-    let app_dir = app_dir()?;
-    Ok(app_dir.join("playlists"))
+    let data_dir = data_dir()?;
+    Ok(data_dir.join("playlists"))
 }
 
 pub fn playlist_file() -> Result<PathBuf, AppError> {
@@ -45,9 +81,425 @@ pub fn playlist_file() -> Result<PathBuf, AppError> {
     Ok(playlist_dir.join("playlist.toml"))
 }
 
+pub fn smart_playlist_file() -> Result<PathBuf, AppError> {
+    let playlist_dir = playlist_dir()?;
+    Ok(playlist_dir.join("smart.toml"))
+}
+
 pub fn current_play_info_file() -> Result<PathBuf, AppError> {
-    let app_dir = app_dir()?;
-    Ok(app_dir.join("current.toml"))
+    let state_dir = state_dir()?;
+    Ok(state_dir.join("current.toml"))
+}
+
+pub fn config_file() -> Result<PathBuf, AppError> {
+    let config_dir = config_dir()?;
+    Ok(config_dir.join("config.toml"))
+}
+
+pub fn session_file() -> Result<PathBuf, AppError> {
+    let data_dir = data_dir()?;
+    Ok(data_dir.join("session.toml"))
+}
+
+pub fn history_file() -> Result<PathBuf, AppError> {
+    let state_dir = state_dir()?;
+    Ok(state_dir.join("history.jsonl"))
+}
+
+pub fn covers_dir() -> Result<PathBuf, AppError> {
+    let cache_dir = cache_dir()?;
+    Ok(cache_dir.join("covers"))
+}
+
+pub fn lyrics_dir() -> Result<PathBuf, AppError> {
+    let cache_dir = cache_dir()?;
+    Ok(cache_dir.join("lyrics"))
+}
+
+// 移动文件或目录, 跨文件系统无法直接重命名时先复制再删除
+fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            move_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        std::fs::remove_dir(from)
+    } else {
+        std::fs::copy(from, to)?;
+        std::fs::remove_file(from)
+    }
+}
+
+/// 旧版本目录中一个文件的迁移结果
+pub enum LegacyMigration {
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// 目标位置已经存在, 没有移动
+    Skipped {
+        from: PathBuf,
+        to: PathBuf,
+    },
+}
+
+/// 旧版本把所有文件都放在 `~/.config/rosesong` 下, 将其移动到对应的 XDG 目录,
+/// 目标位置已经存在的文件保持不变. 设置了 ROSESONG_HOME 时不做迁移.
+/// 迁移在日志初始化之前进行, 返回迁移结果由调用方记录
+pub fn migrate_legacy_dirs() -> Result<Vec<LegacyMigration>, AppError> {
+    let mut report = Vec::new();
+    if rosesong_home().is_some() {
+        return Ok(report);
+    }
+    let legacy_dir = PathBuf::from(std::env::var("HOME")?).join(".config/rosesong");
+    if !legacy_dir.exists() {
+        return Ok(report);
+    }
+    let moves = [
+        ("config.toml", config_file()?),
+        ("playlists", playlist_dir()?),
+        ("session.toml", session_file()?),
+        ("current.toml", current_play_info_file()?),
+        ("history.jsonl", history_file()?),
+        ("logs", logs_dir()?),
+        ("covers", covers_dir()?),
+        ("lyrics", lyrics_dir()?),
+    ];
+    for (name, to) in moves {
+        let from = legacy_dir.join(name);
+        if from == to || !from.exists() {
+            continue;
+        }
+        if to.exists() {
+            report.push(LegacyMigration::Skipped { from, to });
+            continue;
+        }
+        move_path(&from, &to)?;
+        report.push(LegacyMigration::Moved { from, to });
+    }
+    Ok(report)
+}
+
+/// 将秒数格式化为 `1h02m` / `3m05s` 的形式
+pub fn format_duration(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if h > 0 {
+        format!("{h}h{m:02}m")
+    } else {
+        format!("{m}m{s:02}s")
+    }
+}
+
+/// 将 `YYYY-MM-DD` 格式的日期解析为当天零点的本地时间戳
+pub fn parse_date(date: &str) -> Option<i64> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .and_then(|d| d.and_local_timezone(Local).single())
+        .map(|d| d.timestamp())
+}
+
+// rsg 和 rosesong 共用的锁文件, 读写歌曲列表和播放状态时加锁
+fn open_lock_file() -> Result<File, AppError> {
+    let data_dir = data_dir()?;
+    std::fs::create_dir_all(&data_dir)?;
+    Ok(OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(data_dir.join(".lock"))?)
+}
+
+// 获取排他锁, 返回的文件被 drop 时释放
+fn lock_exclusive() -> Result<File, AppError> {
+    let lock = open_lock_file()?;
+    lock.lock()?;
+    Ok(lock)
+}
+
+// 调用方需要已经持有排他锁
+fn write_atomic_unlocked(path: &Path, content: &[u8]) -> Result<(), AppError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension(format!("tmp.{}", std::process::id()));
+    let result = (|| {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)?;
+        // 重命名也需要落盘
+        if let Some(parent) = path.parent() {
+            File::open(parent)?.sync_all()?;
+        }
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// 先写入同目录下的临时文件并 fsync, 再重命名覆盖目标文件, 写入期间持有排他锁,
+/// 崩溃或多个进程同时写入时不会留下不完整的文件
+pub async fn write_atomic(path: &Path, content: String) -> Result<(), AppError> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let _lock = lock_exclusive()?;
+        write_atomic_unlocked(&path, content.as_bytes())
+    })
+    .await?
+}
+
+// 读取文件, 文件不存在时返回 None
+fn read_optional(path: &Path) -> Result<Option<String>, AppError> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 歌曲列表文件的当前版本, 修改 `Playlist` 或 `Track` 的格式时加一,
+/// 并在 `PLAYLIST_MIGRATIONS` 末尾添加对应的迁移
+pub const PLAYLIST_VERSION: u32 = 1;
+/// 播放状态文件的当前版本, 修改时同样需要在 `CURRENT_PLAY_INFO_MIGRATIONS` 中添加迁移
+pub const CURRENT_PLAY_INFO_VERSION: u32 = 1;
+
+/// 将文件内容从版本 n 升级到版本 n + 1, 直接修改 TOML 表,
+/// 不依赖当前的结构体定义, 之后修改结构体时旧的迁移仍然有效
+type Migration = fn(&mut toml::Table) -> Result<(), String>;
+
+/// 第 n 项将版本 n 升级到版本 n + 1
+const PLAYLIST_MIGRATIONS: [Migration; PLAYLIST_VERSION as usize] = [unversioned_playlist_to_v1];
+const CURRENT_PLAY_INFO_MIGRATIONS: [Migration; CURRENT_PLAY_INFO_VERSION as usize] =
+    [unversioned_to_v1];
+
+// 播放状态版本 0 → 1: 版本 0 是还没有 version 字段的旧文件, 其中的字段在版本 1 中都有
+// 默认值, 只需要写入版本号
+fn unversioned_to_v1(_: &mut toml::Table) -> Result<(), String> {
+    Ok(())
+}
+
+// 歌曲列表版本 0 → 1: 版本 0 是还没有 version 字段的旧文件, 歌曲只有 bvid、cid、sid、
+// 标题和作者, 合集没有对应的导入来源
+fn unversioned_playlist_to_v1(playlist: &mut toml::Table) -> Result<(), String> {
+    merge_duplicate_tracks(playlist)?;
+    adopt_season_sources(playlist)
+}
+
+// 旧版本只在导入时按 bvid 替换已有的歌曲, 同一次导入中重复的视频 (例如合集的多个小节中
+// 包含同一视频) 会留下 bvid 和 cid 都相同的多条记录, 只保留第一条
+fn merge_duplicate_tracks(playlist: &mut toml::Table) -> Result<(), String> {
+    let Some(tracks) = playlist.get_mut("tracks") else {
+        return Ok(());
+    };
+    let tracks = tracks
+        .as_array_mut()
+        .ok_or_else(|| "tracks is not an array".to_string())?;
+    let key = |t: &toml::Table| (t.get("bvid").cloned(), t.get("cid").cloned());
+    let mut merged: Vec<toml::Table> = Vec::with_capacity(tracks.len());
+    for track in tracks.drain(..) {
+        let toml::Value::Table(track) = track else {
+            return Err("track is not a table".to_string());
+        };
+        if !merged.iter().any(|t| key(t) == key(&track)) {
+            merged.push(track);
+        }
+    }
+    *tracks = merged.into_iter().map(toml::Value::Table).collect();
+    Ok(())
+}
+
+fn table_str<'a>(table: &'a toml::Table, key: &str) -> Option<&'a str> {
+    table.get(key).and_then(toml::Value::as_str)
+}
+
+// 旧版本导入的合集没有导入来源记录, 为其生成来源, 以当前列表中属于该合集的歌曲
+// 作为上次同步的状态. 旧版本导入单个视频时拒绝导入合集也会记录合集, 这样的合集
+// 没有歌曲, 不生成来源, 避免下次同步时导入整个合集
+fn adopt_season_sources(playlist: &mut toml::Table) -> Result<(), String> {
+    let tracks = playlist.get("tracks").and_then(toml::Value::as_array);
+    let mut sources = Vec::new();
+    for season in playlist
+        .get("seasons")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let season = season
+            .as_table()
+            .ok_or_else(|| "season is not a table".to_string())?;
+        let id = table_str(season, "id").ok_or_else(|| "season without id".to_string())?;
+        let mut bvids: Vec<toml::Value> = Vec::new();
+        for track in tracks
+            .into_iter()
+            .flatten()
+            .filter_map(toml::Value::as_table)
+        {
+            if table_str(track, "sid") != Some(id) {
+                continue;
+            }
+            if let Some(bvid) = track.get("bvid") {
+                if !bvids.contains(bvid) {
+                    bvids.push(bvid.clone());
+                }
+            }
+        }
+        if bvids.is_empty() {
+            continue;
+        }
+        let mut source = toml::Table::new();
+        source.insert("kind".to_string(), "season".into());
+        source.insert("id".to_string(), id.into());
+        source.insert(
+            "title".to_string(),
+            table_str(season, "title").unwrap_or_default().into(),
+        );
+        source.insert("bvids".to_string(), toml::Value::Array(bvids));
+        sources.push(toml::Value::Table(source));
+    }
+    if !sources.is_empty() {
+        playlist.insert("sources".to_string(), toml::Value::Array(sources));
+    }
+    Ok(())
+}
+
+fn corrupt_file(path: &Path, message: impl Into<String>) -> AppError {
+    AppError::CorruptFile {
+        path: path.display().to_string(),
+        message: message.into(),
+    }
+}
+
+// 依次执行迁移将 TOML 表升级到当前版本, 只修改内存中的内容, 返回原来的版本
+fn upgrade_table(
+    path: &Path,
+    table: &mut toml::Table,
+    version: u32,
+    migrations: &[Migration],
+) -> Result<u32, AppError> {
+    let file_version = match table.get("version") {
+        None => 0,
+        Some(value) => value
+            .as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| corrupt_file(path, "invalid version"))?,
+    };
+    if file_version > version {
+        return Err(AppError::UnsupportedVersion {
+            path: path.display().to_string(),
+            version: file_version,
+        });
+    }
+    for from in file_version..version {
+        migrations[from as usize](table).map_err(|message| {
+            log::error!(
+                "Failed to migrate {} from version {}: {}",
+                path.display(),
+                from,
+                message
+            );
+            AppError::Migration {
+                path: path.display().to_string(),
+                from,
+                message,
+            }
+        })?;
+        table.insert("version".to_string(), i64::from(from + 1).into());
+    }
+    Ok(file_version)
+}
+
+// 升级旧版本的文件并写回, 写入前将原文件备份为 `<文件名>.v<版本>.bak`.
+// 调用方需要已经持有排他锁
+fn migrate_table(
+    path: &Path,
+    table: &mut toml::Table,
+    version: u32,
+    migrations: &[Migration],
+) -> Result<(), AppError> {
+    let file_version = upgrade_table(path, table, version, migrations)?;
+    if file_version == version {
+        return Ok(());
+    }
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let backup = path.with_file_name(format!("{file_name}.v{file_version}.bak"));
+    if !backup.exists() {
+        std::fs::copy(path, &backup)?;
+    }
+    let content = toml::to_string(table)
+        .map_err(|_| AppError::DataParsing("Failed to serialize migrated TOML".to_string()))?;
+    write_atomic_unlocked(path, content.as_bytes())?;
+    log::info!(
+        "migrated {} from version {} to {}, backup saved as {}",
+        path.display(),
+        file_version,
+        version,
+        backup.display()
+    );
+    Ok(())
+}
+
+// 读取带有版本号的 TOML 文件, 旧版本的文件先升级到当前版本. 文件不存在时使用默认值,
+// 内容无法解析时返回带有文件路径的错误. 调用方需要已经持有排他锁
+fn read_versioned_unlocked<T: DeserializeOwned + Default>(
+    path: &Path,
+    version: u32,
+    migrations: &[Migration],
+) -> Result<T, AppError> {
+    let Some(content) = read_optional(path)? else {
+        return Ok(T::default());
+    };
+    let mut table: toml::Table =
+        toml::from_str(&content).map_err(|e| corrupt_file(path, e.message()))?;
+    migrate_table(path, &mut table, version, migrations)?;
+    table
+        .try_into()
+        .map_err(|e: toml::de::Error| corrupt_file(path, e.message()))
+}
+
+// 读取时可能需要迁移并写回文件, 所以持有排他锁
+async fn read_versioned_toml<T: DeserializeOwned + Default + Send + 'static>(
+    path: PathBuf,
+    version: u32,
+    migrations: &'static [Migration],
+) -> Result<T, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let _lock = lock_exclusive()?;
+        read_versioned_unlocked(&path, version, migrations)
+    })
+    .await?
+}
+
+/// 持有排他锁完成歌曲列表的读取、修改和保存, rsg 和 rosesong 同时修改时不会互相覆盖.
+/// 内容没有变化时不写入文件
+pub async fn update_playlist<R: Send + 'static>(
+    update: impl FnOnce(&mut Playlist) -> R + Send + 'static,
+) -> Result<R, AppError> {
+    let path = playlist_file()?;
+    tokio::task::spawn_blocking(move || {
+        let _lock = lock_exclusive()?;
+        let mut playlist: Playlist =
+            read_versioned_unlocked(&path, PLAYLIST_VERSION, &PLAYLIST_MIGRATIONS)?;
+        let old_content = toml::to_string(&playlist).ok();
+        let result = update(&mut playlist);
+        let content = toml::to_string(&playlist).map_err(|_| {
+            AppError::DataParsing("Failed to serialize Playlist to TOML".to_string())
+        })?;
+        if old_content.as_ref() != Some(&content) {
+            write_atomic_unlocked(&path, content.as_bytes())?;
+        }
+        Ok(result)
+    })
+    .await?
 }
 
 pub async fn save_playlist_to_file(playlist: &Playlist) -> Result<(), AppError> {
@@ -55,7 +507,7 @@ pub async fn save_playlist_to_file(playlist: &Playlist) -> Result<(), AppError>
     let file_path = playlist_file()?;
     let content = toml::to_string(playlist)
         .map_err(|_| AppError::DataParsing("Failed to serialize Playlist to TOML".to_string()))?;
-    tokio::fs::write(&file_path, content).await?;
+    write_atomic(&file_path, content).await?;
     Ok(())
 }
 
@@ -65,23 +517,133 @@ pub async fn save_current_play_info(current_play_info: &CurrentPlayInfo) -> Resu
     let content = toml::to_string(current_play_info).map_err(|_| {
         AppError::DataParsing("Failed to serialize CurrentPlayInfo to TOML".to_string())
     })?;
-    tokio::fs::write(&file_path, content).await?;
+    write_atomic(&file_path, content).await?;
     Ok(())
 }
 
-pub async fn get_playlist() -> Option<Playlist> {
-    let file_path = playlist_file().ok()?;
-    let content = tokio::fs::read_to_string(&file_path).await.ok()?;
-    toml::from_str::<Playlist>(&content).ok()
+/// 读取歌曲列表, 文件不存在时返回空列表, 文件损坏时返回 `AppError::CorruptFile`
+pub async fn get_playlist() -> Result<Playlist, AppError> {
+    read_versioned_toml(playlist_file()?, PLAYLIST_VERSION, &PLAYLIST_MIGRATIONS).await
 }
 
-pub async fn get_current_play_info() -> Option<CurrentPlayInfo> {
-    let file_path = current_play_info_file().ok()?;
-    let content = tokio::fs::read_to_string(&file_path).await.ok()?;
-    toml::from_str::<CurrentPlayInfo>(&content).ok()
+/// 读取智能列表定义, 文件不存在时返回空列表
+pub async fn get_smart_playlists() -> Result<Vec<SmartPlaylist>, AppError> {
+    let file_path = smart_playlist_file()?;
+    if !file_path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = tokio::fs::read_to_string(&file_path).await?;
+    Ok(toml::from_str::<SmartPlaylists>(&content)?.smart)
+}
+
+/// 读取播放状态, 文件不存在时返回默认状态, 文件损坏时返回 `AppError::CorruptFile`
+pub async fn get_current_play_info() -> Result<CurrentPlayInfo, AppError> {
+    read_versioned_toml(
+        current_play_info_file()?,
+        CURRENT_PLAY_INFO_VERSION,
+        &CURRENT_PLAY_INFO_MIGRATIONS,
+    )
+    .await
 }
 
 pub async fn is_playlist_empty() -> Result<bool, AppError> {
-    let playlist = get_playlist().await;
-    Ok(playlist.is_none() || playlist.is_some() && playlist.unwrap().tracks.is_empty())
+    Ok(get_playlist().await?.tracks.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::SourceKind;
+
+    fn upgrade<T: DeserializeOwned>(
+        fixture: &str,
+        version: u32,
+        migrations: &[Migration],
+    ) -> (u32, T) {
+        let mut table: toml::Table = toml::from_str(fixture).unwrap();
+        let from =
+            upgrade_table(Path::new("fixture.toml"), &mut table, version, migrations).unwrap();
+        assert_eq!(table["version"].as_integer(), Some(i64::from(version)));
+        (from, table.try_into().unwrap())
+    }
+
+    #[test]
+    fn upgrades_unversioned_playlist() {
+        let (from, playlist): (u32, Playlist) = upgrade(
+            include_str!("../tests/fixtures/playlist_v0.toml"),
+            PLAYLIST_VERSION,
+            &PLAYLIST_MIGRATIONS,
+        );
+        assert_eq!(from, 0);
+        assert_eq!(playlist.version, PLAYLIST_VERSION);
+
+        // 重复的歌曲只保留第一条
+        let ids: Vec<(&str, &str)> = playlist
+            .tracks
+            .iter()
+            .map(|t| (t.bvid.as_str(), t.cid.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                ("BV1xx411c7mD", "1001"),
+                ("BV1yy411c7mE", "1003"),
+                ("BV1zz411c7mF", "1004")
+            ]
+        );
+        assert_eq!(playlist.tracks[0].sid.as_deref(), Some("2001"));
+        assert_eq!(playlist.seasons.len(), 2);
+
+        // 有歌曲的合集生成导入来源, 没有歌曲的合集不生成
+        assert_eq!(playlist.sources.len(), 1);
+        let source = &playlist.sources[0];
+        assert_eq!(
+            (source.kind, source.id.as_str()),
+            (SourceKind::Season, "2001")
+        );
+        assert_eq!(source.title, "合集甲");
+        assert_eq!(source.bvids, ["BV1xx411c7mD", "BV1zz411c7mF"]);
+    }
+
+    #[test]
+    fn upgrades_unversioned_current_play_info() {
+        let (from, info): (u32, CurrentPlayInfo) = upgrade(
+            include_str!("../tests/fixtures/current_play_info_v0.toml"),
+            CURRENT_PLAY_INFO_VERSION,
+            &CURRENT_PLAY_INFO_MIGRATIONS,
+        );
+        assert_eq!(from, 0);
+        assert_eq!(info.version, CURRENT_PLAY_INFO_VERSION);
+        assert_eq!((info.index, info.volume), (0, 80));
+        assert_eq!(info.playing_sid.as_deref(), Some("2001"));
+        assert_eq!(info.current_tracks.len(), 2);
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let mut table: toml::Table =
+            toml::from_str("version = 99\ntracks = []\nseasons = []").unwrap();
+        let result = upgrade_table(
+            Path::new("fixture.toml"),
+            &mut table,
+            PLAYLIST_VERSION,
+            &PLAYLIST_MIGRATIONS,
+        );
+        assert!(matches!(
+            result,
+            Err(AppError::UnsupportedVersion { version: 99, .. })
+        ));
+    }
+
+    #[test]
+    fn reports_failed_step() {
+        let mut table: toml::Table = toml::from_str("tracks = 1\nseasons = []").unwrap();
+        let result = upgrade_table(
+            Path::new("fixture.toml"),
+            &mut table,
+            PLAYLIST_VERSION,
+            &PLAYLIST_MIGRATIONS,
+        );
+        assert!(matches!(result, Err(AppError::Migration { from: 0, .. })));
+    }
 }